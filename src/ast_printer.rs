@@ -1,34 +1,101 @@
-use crate::{expr::*, token::LiteralKind};
+use crate::{
+    expr::*,
+    stmt::{self, StatementVisitor, Stmt},
+    token::Token,
+};
 
 pub struct AstPrinter {}
 
 impl AstPrinter {
+    /// ```
+    /// use codecrafters_interpreter::{
+    ///     ast_printer::AstPrinter, parser::Parser, scanner::Scanner, token::LiteralKind,
+    /// };
+    ///
+    /// let (tokens, _) = Scanner::new("nil".to_string()).into_parts();
+    /// assert_eq!(tokens[0].literal(), &LiteralKind::Nil);
+    /// assert_eq!(tokens[0].to_string(), "NIL nil null");
+    ///
+    /// let expr = Parser::new(tokens).parse_expression().unwrap();
+    /// assert_eq!(AstPrinter {}.print(expr), "nil");
+    /// ```
     pub fn print(&mut self, expr: Expr) -> String {
         expr.accept(self)
     }
 
+    /// Prints a whole program, one top-level statement per line.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::{ast_printer::AstPrinter, parser::Parser, scanner::Scanner};
+    ///
+    /// let source = "var f = fun(a){ return a; }; print f(1) or nil;";
+    /// let (tokens, _) = Scanner::new(source.to_string()).into_parts();
+    /// let statements = Parser::new(tokens).parse().unwrap();
+    /// assert_eq!(
+    ///     AstPrinter {}.print_statements(&statements),
+    ///     "(var f (lambda (a) (return a)))\n(print (or (call f 1.0) nil))"
+    /// );
+    /// ```
+    pub fn print_statements(&mut self, statements: &[Stmt]) -> String {
+        statements
+            .iter()
+            .map(|statement| statement.accept(self))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    // `(name part part ...)`, leaving out empty parts.
+    fn group(&mut self, name: &str, parts: &[String]) -> String {
+        let mut string = format!("({name}");
+        for part in parts.iter().filter(|part| !part.is_empty()) {
+            string.push(' ');
+            string.push_str(part);
+        }
+        string.push(')');
+        string
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) -> String {
+        statements
+            .iter()
+            .map(|statement| statement.accept(self))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    fn params(params: &[Token], variadic: bool) -> String {
+        let mut params: Vec<String> = params
+            .iter()
+            .map(|param| param.lexeme().to_string())
+            .collect();
+        if let Some(rest) = params.last_mut().filter(|_| variadic) {
+            rest.insert_str(0, "...");
+        }
+        format!("({})", params.join(" "))
+    }
+
     fn parenthesize(&mut self, name: String, exprs: &[Expr]) -> String {
         let mut string = String::new();
-        string.push_str("(");
+        string.push('(');
         string.push_str(&name);
         for expr in exprs.iter() {
             string.push(' ');
             let expression = expr.accept(self);
             string.push_str(&expression);
         }
-        string.push_str(")");
+        string.push(')');
         string
     }
 }
 
 impl ExpressionVisitor<String> for AstPrinter {
-    fn visit_assignment(&mut self, _expr: &Assignment) -> String {
-        todo!()
+    fn visit_assignment(&mut self, expr: &Assignment) -> String {
+        format!("(= {} {})", expr.name.lexeme, expr.value.accept(self))
     }
 
     fn visit_binary(&mut self, expr: &Binary) -> String {
         self.parenthesize(
-            expr.operator.lexeme.clone(),
+            expr.operator.lexeme.to_string(),
             &[*expr.left.clone(), *expr.right.clone()],
         )
     }
@@ -38,41 +105,246 @@ impl ExpressionVisitor<String> for AstPrinter {
     }
 
     fn visit_literal(&self, expr: &Literal) -> String {
-        if expr.value == LiteralKind::Nil {
-            return "nil".to_string();
-        }
         String::from(expr.value.clone())
     }
 
-    fn visit_logical(&mut self, _expr: &Logical) -> String {
-        todo!()
+    fn visit_logical(&mut self, expr: &Logical) -> String {
+        self.parenthesize(
+            expr.operator.lexeme.to_string(),
+            &[*expr.left.clone(), *expr.right.clone()],
+        )
     }
 
     fn visit_unary(&mut self, expr: &Unary) -> String {
-        self.parenthesize(expr.operator.lexeme.clone(), &[*expr.right.clone()])
+        self.parenthesize(expr.operator.lexeme.to_string(), &[*expr.right.clone()])
     }
 
-    fn visit_variable(&mut self, _expr: &Variable) -> String {
-        todo!()
+    fn visit_postfix(&mut self, expr: &Postfix) -> String {
+        self.parenthesize(
+            format!("postfix{}", expr.operator.lexeme),
+            &[*expr.target.clone()],
+        )
     }
 
-    fn visit_call(&mut self, _expr: &Call) -> String {
-        todo!()
+    fn visit_variable(&mut self, expr: &Variable) -> String {
+        expr.name.lexeme.to_string()
     }
 
-    fn visit_get(&mut self, _expr: &Get) -> String {
-        todo!()
+    fn visit_call(&mut self, expr: &Call) -> String {
+        let mut exprs = vec![*expr.callee.clone()];
+        exprs.extend(expr.arguments.iter().cloned());
+        self.parenthesize("call".to_owned(), &exprs)
     }
 
-    fn visit_set(&mut self, _expr: &Set) -> String {
-        todo!()
+    fn visit_lambda(&mut self, expr: &Lambda) -> String {
+        let params = Self::params(&expr.params, expr.variadic);
+        let body = self.statements(&expr.body);
+        self.group("lambda", &[params, body])
+    }
+
+    fn visit_list(&mut self, expr: &List) -> String {
+        let elements: Vec<String> = expr
+            .elements
+            .iter()
+            .map(|element| element.accept(self))
+            .collect();
+        format!("[{}]", elements.join(", "))
+    }
+
+    fn visit_map(&mut self, expr: &Map) -> String {
+        let entries: Vec<String> = expr
+            .entries
+            .iter()
+            .map(|(key, value)| format!("{}: {}", key.accept(self), value.accept(self)))
+            .collect();
+        format!("{{{}}}", entries.join(", "))
+    }
+
+    fn visit_index(&mut self, expr: &Index) -> String {
+        self.parenthesize(
+            "index".to_owned(),
+            &[*expr.object.clone(), *expr.index.clone()],
+        )
+    }
+
+    fn visit_set_index(&mut self, expr: &SetIndex) -> String {
+        self.parenthesize(
+            "index=".to_owned(),
+            &[
+                *expr.object.clone(),
+                *expr.index.clone(),
+                *expr.value.clone(),
+            ],
+        )
+    }
+
+    fn visit_get(&mut self, expr: &Get) -> String {
+        format!("(. {} {})", expr.object.accept(self), expr.name.lexeme)
+    }
+
+    fn visit_set(&mut self, expr: &Set) -> String {
+        format!(
+            "(= (. {} {}) {})",
+            expr.object.accept(self),
+            expr.name.lexeme,
+            expr.value.accept(self)
+        )
     }
 
     fn visit_this(&mut self, _expr: &This) -> String {
-        todo!()
+        "this".to_string()
+    }
+
+    fn visit_super(&mut self, expr: &Super) -> String {
+        format!("(super {})", expr.method.lexeme)
+    }
+
+    fn visit_interpolation(&mut self, expr: &Interpolation) -> String {
+        self.parenthesize("interpolate".to_owned(), &expr.parts)
+    }
+
+    fn visit_loop(&mut self, expr: &Loop) -> String {
+        let body = self.statements(&expr.body);
+        self.group("loop", &[body])
+    }
+
+    fn visit_block_expression(&mut self, expr: &BlockExpression) -> String {
+        let body = self.statements(&expr.statements);
+        let value = match &expr.value {
+            Some(value) => value.accept(self),
+            None => String::new(),
+        };
+        self.group("do", &[body, value])
+    }
+}
+
+impl StatementVisitor<String> for AstPrinter {
+    fn visit_expression(&mut self, stmt: &stmt::Expression) -> String {
+        self.parenthesize(";".to_owned(), &[*stmt.expression.clone()])
+    }
+
+    fn visit_print(&mut self, stmt: &stmt::Print) -> String {
+        self.parenthesize("print".to_owned(), &stmt.expressions)
+    }
+
+    fn visit_var(&mut self, stmt: &stmt::Var) -> String {
+        let initializer = match &stmt.initializer {
+            Some(initializer) => initializer.accept(self),
+            None => String::new(),
+        };
+        let keyword = if stmt.constant { "const" } else { "var" };
+        self.group(keyword, &[stmt.name.lexeme.to_string(), initializer])
+    }
+
+    fn visit_block(&mut self, stmt: &stmt::Block) -> String {
+        let body = self.statements(&stmt.statements);
+        self.group("block", &[body])
+    }
+
+    /// `else if` chains print flat, one `elif` group per link, rather than
+    /// as ifs nested in each other's else branch.
+    fn visit_if(&mut self, stmt: &stmt::If) -> String {
+        let mut parts = vec![stmt.condition.accept(self), stmt.then_branch.accept(self)];
+        let mut else_branch = stmt.else_branch.as_deref();
+        while let Some(Stmt::If(else_if)) = else_branch {
+            let condition = else_if.condition.accept(self);
+            let then_branch = else_if.then_branch.accept(self);
+            parts.push(self.group("elif", &[condition, then_branch]));
+            else_branch = else_if.else_branch.as_deref();
+        }
+        if let Some(else_branch) = else_branch {
+            parts.push(else_branch.accept(self));
+        }
+        self.group("if", &parts)
+    }
+
+    fn visit_while(&mut self, stmt: &stmt::While) -> String {
+        let condition = stmt.condition.accept(self);
+        let body = stmt.body.accept(self);
+        let increment = match &stmt.increment {
+            Some(increment) => increment.accept(self),
+            None => String::new(),
+        };
+        self.group("while", &[condition, body, increment])
+    }
+
+    fn visit_for_in(&mut self, stmt: &stmt::ForIn) -> String {
+        let iterable = stmt.iterable.accept(self);
+        let body = stmt.body.accept(self);
+        self.group(
+            "for-in",
+            &[stmt.variable.lexeme.to_string(), iterable, body],
+        )
+    }
+
+    fn visit_function(&mut self, stmt: &stmt::Function) -> String {
+        let params = match stmt.getter {
+            true => String::new(),
+            false => Self::params(&stmt.params, stmt.variadic),
+        };
+        let body = self.statements(&stmt.body);
+        self.group("fun", &[stmt.name.lexeme.to_string(), params, body])
+    }
+
+    fn visit_return(&mut self, stmt: &stmt::Return) -> String {
+        let value = match &stmt.value {
+            Some(value) => value.accept(self),
+            None => "nil".to_string(),
+        };
+        self.group("return", &[value])
+    }
+
+    fn visit_class(&mut self, stmt: &stmt::Class) -> String {
+        let superclass = match &stmt.super_class {
+            Some(superclass) => format!("< {}", superclass.accept(self)),
+            None => String::new(),
+        };
+        let methods = self.statements(&stmt.methods);
+        let class_methods = match stmt.class_methods.is_empty() {
+            true => String::new(),
+            false => {
+                let class_methods = self.statements(&stmt.class_methods);
+                self.group("class", &[class_methods])
+            }
+        };
+        self.group(
+            "class",
+            &[
+                stmt.name.lexeme.to_string(),
+                superclass,
+                methods,
+                class_methods,
+            ],
+        )
+    }
+
+    fn visit_switch(&mut self, stmt: &stmt::Switch) -> String {
+        let mut parts = vec![stmt.scrutinee.accept(self)];
+        for (patterns, body) in stmt.arms.iter() {
+            let patterns: Vec<String> = patterns
+                .iter()
+                .map(|pattern| pattern.accept(self))
+                .collect();
+            let patterns = format!("({})", patterns.join(" "));
+            let body = self.statements(body);
+            parts.push(self.group("case", &[patterns, body]));
+        }
+        if let Some(default) = &stmt.default {
+            let body = self.statements(default);
+            parts.push(self.group("default", &[body]));
+        }
+        self.group("switch", &parts)
+    }
+
+    fn visit_break(&mut self, stmt: &stmt::Break) -> String {
+        let value = match &stmt.value {
+            Some(value) => value.accept(self),
+            None => String::new(),
+        };
+        self.group("break", &[value])
     }
 
-    fn visit_super(&mut self, _expr: &Super) -> String {
-        todo!()
+    fn visit_continue(&mut self, _stmt: &stmt::Continue) -> String {
+        "(continue)".to_string()
     }
 }
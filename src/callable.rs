@@ -0,0 +1,322 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{Debug, Display},
+    rc::Rc,
+};
+
+use crate::{
+    environement::Environment,
+    expr,
+    interpreter::{Exit, Interpreter, RuntimeError, Value},
+    stmt::{self, Stmt},
+    token::Token,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match self {
+            Arity::Exact(arity) => count == *arity,
+            Arity::AtLeast(arity) => count >= *arity,
+        }
+    }
+}
+
+impl Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arity::Exact(arity) => write!(f, "{}", arity),
+            Arity::AtLeast(arity) => write!(f, "at least {}", arity),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: Option<String>,
+    pub params: Rc<Vec<Token>>,
+    pub variadic: bool,
+    pub body: Rc<Vec<Stmt>>,
+    pub closure: Rc<RefCell<Environment>>,
+    pub is_initializer: bool,
+    pub is_getter: bool,
+}
+
+impl Function {
+    pub fn new(declaration: &stmt::Function, closure: Rc<RefCell<Environment>>) -> Self {
+        Function {
+            name: Some(declaration.name.lexeme.to_string()),
+            params: Rc::new(declaration.params.clone()),
+            variadic: declaration.variadic,
+            body: Rc::new(declaration.body.clone()),
+            closure,
+            is_initializer: false,
+            is_getter: declaration.getter,
+        }
+    }
+
+    pub fn method(
+        declaration: &stmt::Function,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        Function {
+            is_initializer,
+            ..Function::new(declaration, closure)
+        }
+    }
+
+    pub fn lambda(lambda: &expr::Lambda, closure: Rc<RefCell<Environment>>) -> Self {
+        Function {
+            name: None,
+            params: Rc::new(lambda.params.clone()),
+            variadic: lambda.variadic,
+            body: Rc::new(lambda.body.clone()),
+            closure,
+            is_initializer: false,
+            is_getter: false,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("<fn>")
+    }
+
+    pub fn arity(&self) -> Arity {
+        match self.variadic {
+            true => Arity::AtLeast(self.params.len() - 1),
+            false => Arity::Exact(self.params.len()),
+        }
+    }
+
+    /// Returns a copy of this method whose closure has `this` bound to
+    /// `instance`.
+    pub fn bind(&self, instance: Value) -> Function {
+        let mut environment = Environment::new_with_enclosing(Rc::clone(&self.closure));
+        environment.define("this".to_string(), instance);
+        Function {
+            closure: Rc::new(RefCell::new(environment)),
+            ..self.clone()
+        }
+    }
+
+    pub fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Exit> {
+        let mut environment = Environment::new_with_enclosing(Rc::clone(&self.closure));
+        let fixed = self.params.len() - usize::from(self.variadic);
+        let mut arguments = arguments.into_iter();
+        for (param, argument) in self.params[..fixed].iter().zip(arguments.by_ref()) {
+            environment.define(param.lexeme.to_string(), argument);
+        }
+        if self.variadic {
+            environment.define(
+                self.params[fixed].lexeme.to_string(),
+                Value::List(Rc::new(RefCell::new(arguments.collect()))),
+            );
+        }
+
+        let result = match interpreter.execute_block(&self.body, environment) {
+            Ok(()) => Value::Nil,
+            Err(Exit::Return(value)) => value,
+            Err(exit) => return Err(exit),
+        };
+
+        // An initializer always hands back the instance it was bound to.
+        match self.is_initializer {
+            true => Ok(self.closure.borrow().local("this").unwrap_or(result)),
+            false => Ok(result),
+        }
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "<fn {}>", name),
+            None => write!(f, "<fn>"),
+        }
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.body, &other.body) && Rc::ptr_eq(&self.closure, &other.closure)
+    }
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: String,
+    pub superclass: Option<Rc<Class>>,
+    pub methods: HashMap<String, Function>,
+    pub class_methods: HashMap<String, Function>,
+}
+
+impl Class {
+    /// Looks up a method declared with `class` on this class or its
+    /// superclasses. These are called on the class itself and have no `this`.
+    pub fn get(&self, name: &Token) -> Result<Value, Exit> {
+        match self.find_class_method(name.lexeme()) {
+            Some(method) => Ok(Value::Function(method)),
+            None => Err(RuntimeError::new(
+                name.line,
+                &format!("Undefined property '{}'.", name.lexeme),
+            )
+            .into()),
+        }
+    }
+
+    fn find_class_method(&self, name: &str) -> Option<Function> {
+        match self.class_methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self
+                .superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_class_method(name)),
+        }
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<Function> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self
+                .superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_method(name)),
+        }
+    }
+
+    /// Whether this class is `other` or inherits from it.
+    pub fn is_subclass_of(&self, other: &Class) -> bool {
+        self == other
+            || self
+                .superclass
+                .as_ref()
+                .is_some_and(|superclass| superclass.is_subclass_of(other))
+    }
+
+    pub fn arity(&self) -> Arity {
+        match self.find_method("init") {
+            Some(initializer) => initializer.arity(),
+            None => Arity::Exact(0),
+        }
+    }
+
+    pub fn call(
+        class: &Rc<Class>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Exit> {
+        let instance = Value::Instance(Rc::new(RefCell::new(Instance::new(Rc::clone(class)))));
+        if let Some(initializer) = class.find_method("init") {
+            initializer
+                .bind(instance.clone())
+                .call(interpreter, arguments)?;
+        }
+        Ok(instance)
+    }
+}
+
+impl Display for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<class {}>", self.name)
+    }
+}
+
+impl PartialEq for Class {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: HashMap<String, Value>,
+}
+
+impl Instance {
+    pub fn new(class: Rc<Class>) -> Self {
+        Instance {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Looks a property up on `instance`, preferring fields over methods.
+    /// Methods come back bound to the instance.
+    pub fn get(instance: &Rc<RefCell<Instance>>, name: &Token) -> Result<Value, Exit> {
+        let this = instance.borrow();
+        if let Some(value) = this.fields.get(name.lexeme()) {
+            return Ok(value.clone());
+        }
+
+        match this.class.find_method(name.lexeme()) {
+            Some(method) => Ok(Value::Function(
+                method.bind(Value::Instance(Rc::clone(instance))),
+            )),
+            None => Err(RuntimeError::new(
+                name.line,
+                &format!("Undefined property '{}'.", name.lexeme),
+            )
+            .into()),
+        }
+    }
+
+    pub fn set(&mut self, name: &Token, value: Value) {
+        self.fields.insert(name.lexeme.to_string(), value);
+    }
+}
+
+impl Display for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
+
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+pub type NativeFn = fn(&mut Interpreter, &Token, Vec<Value>) -> Result<Value, Exit>;
+
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub arity: Arity,
+    pub function: NativeFn,
+}
+
+impl NativeFunction {
+    pub fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Exit> {
+        (self.function)(interpreter, paren, arguments)
+    }
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
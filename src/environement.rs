@@ -1,21 +1,34 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
 
 use crate::{
-    interpreter::Exit,
-    report,
-    token::{LiteralKind, Token},
+    interpreter::{Exit, RuntimeError, Value},
+    token::Token,
 };
 
 #[derive(Debug, Clone)]
 pub struct Environment {
-    values: HashMap<String, LiteralKind>,
+    // `None` marks a variable declared with `var a;` and not yet assigned.
+    values: HashMap<String, Option<Value>>,
+    // Names bound with `const` in this scope.
+    constants: HashSet<String>,
     enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Environment {
     pub fn new() -> Self {
         Environment {
             values: HashMap::new(),
+            constants: HashSet::new(),
             enclosing: None,
         }
     }
@@ -23,35 +36,150 @@ impl Environment {
     pub fn new_with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
         Environment {
             values: HashMap::new(),
+            constants: HashSet::new(),
             enclosing: Some(enclosing),
         }
     }
 
-    pub fn define(&mut self, name: String, value: LiteralKind) {
-        self.values.insert(name, value);
+    pub fn define(&mut self, name: String, value: Value) {
+        self.constants.remove(&name);
+        self.values.insert(name, Some(value));
     }
 
-    pub fn get(&self, name: &Token) -> Result<LiteralKind, Exit> {
-        if let Some(value) = self.values.get(&name.lexeme) {
-            Ok(value.clone())
-        } else if self.enclosing.is_some() {
-            Ok(self.enclosing.as_ref().unwrap().borrow().get(name)?)
+    /// Binds `name` like `define`, but refuses any later assignment to it.
+    pub fn define_constant(&mut self, name: String, value: Value) {
+        self.constants.insert(name.clone());
+        self.values.insert(name, Some(value));
+    }
+
+    pub fn declare(&mut self, name: String) {
+        self.constants.remove(&name);
+        self.values.insert(name, None);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, Exit> {
+        if let Some(value) = self.values.get(name.lexeme()) {
+            value.clone().ok_or_else(|| {
+                RuntimeError::new(
+                    name.line,
+                    &format!("Uninitialized variable '{}'.", name.lexeme),
+                )
+                .into()
+            })
+        } else if let Some(enclosing) = &self.enclosing {
+            Ok(enclosing.borrow().get(name)?)
         } else {
-            report(name.line, &format!("Undefined variable '{}'.", name.lexeme));
-            Err(Exit::RuntimeError)
+            Err(
+                RuntimeError::new(name.line, &format!("Undefined variable '{}'.", name.lexeme))
+                    .into(),
+            )
         }
     }
 
-    pub fn assign(&mut self, name: &Token, value: LiteralKind) -> Result<(), Exit> {
-        if self.values.contains_key(&name.lexeme) {
-            self.values.insert(name.lexeme.clone(), value);
+    /// Reads an initialized `name` from this scope alone.
+    pub fn local(&self, name: &str) -> Option<Value> {
+        self.values.get(name).cloned().flatten()
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), Exit> {
+        if self.values.contains_key(name.lexeme()) {
+            self.check_mutable(name)?;
+            self.values.insert(name.lexeme.to_string(), Some(value));
             Ok(())
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.borrow_mut().assign(name, value)?;
             Ok(())
         } else {
-            report(name.line, &format!("Undefined variable '{}'.", name.lexeme));
-            Err(Exit::RuntimeError)
+            Err(
+                RuntimeError::new(name.line, &format!("Undefined variable '{}'.", name.lexeme))
+                    .into(),
+            )
+        }
+    }
+
+    /// Reads `name` from the scope exactly `distance` hops up the chain, as
+    /// computed by the resolver, without searching the scopes in between.
+    ///
+    /// ```
+    /// use std::{cell::RefCell, rc::Rc};
+    ///
+    /// use codecrafters_interpreter::{
+    ///     environement::Environment,
+    ///     interpreter::Value,
+    ///     token::{LiteralKind, Token, TokenKind},
+    /// };
+    ///
+    /// let globals = Rc::new(RefCell::new(Environment::new()));
+    /// globals.borrow_mut().define("a".to_string(), Value::Number(1.0));
+    /// let middle = Rc::new(RefCell::new(Environment::new_with_enclosing(globals)));
+    /// middle.borrow_mut().define("a".to_string(), Value::Number(2.0));
+    /// let mut inner = Environment::new_with_enclosing(middle);
+    /// inner.define("a".to_string(), Value::Number(3.0));
+    ///
+    /// let a = Token::new(TokenKind::Identifier, "a".to_string(), LiteralKind::Nil, 1);
+    /// assert_eq!(inner.get_at(0, &a).unwrap(), Value::Number(3.0));
+    /// assert_eq!(inner.get_at(1, &a).unwrap(), Value::Number(2.0));
+    /// assert_eq!(inner.get_at(2, &a).unwrap(), Value::Number(1.0));
+    /// ```
+    pub fn get_at(&self, distance: usize, name: &Token) -> Result<Value, Exit> {
+        match distance {
+            0 => self.get_local(name),
+            _ => self.ancestor(distance, name)?.borrow().get_local(name),
+        }
+    }
+
+    pub fn assign_at(&mut self, distance: usize, name: &Token, value: Value) -> Result<(), Exit> {
+        match distance {
+            0 => {
+                self.check_mutable(name)?;
+                self.values.insert(name.lexeme.to_string(), Some(value))
+            }
+            _ => {
+                let ancestor = self.ancestor(distance, name)?;
+                let mut ancestor = ancestor.borrow_mut();
+                ancestor.check_mutable(name)?;
+                ancestor.values.insert(name.lexeme.to_string(), Some(value))
+            }
+        };
+        Ok(())
+    }
+
+    fn check_mutable(&self, name: &Token) -> Result<(), Exit> {
+        match self.constants.contains(name.lexeme()) {
+            true => Err(RuntimeError::new(
+                name.line,
+                &format!("Cannot assign to constant '{}'.", name.lexeme),
+            )
+            .into()),
+            false => Ok(()),
+        }
+    }
+
+    fn ancestor(&self, distance: usize, name: &Token) -> Result<Rc<RefCell<Environment>>, Exit> {
+        let mut environment = self.enclosing.clone();
+        for _ in 1..distance {
+            environment =
+                environment.and_then(|environment| environment.borrow().enclosing.clone());
+        }
+
+        environment.ok_or_else(|| {
+            RuntimeError::new(name.line, &format!("Undefined variable '{}'.", name.lexeme)).into()
+        })
+    }
+
+    fn get_local(&self, name: &Token) -> Result<Value, Exit> {
+        match self.values.get(name.lexeme()) {
+            Some(Some(value)) => Ok(value.clone()),
+            Some(None) => Err(RuntimeError::new(
+                name.line,
+                &format!("Uninitialized variable '{}'.", name.lexeme),
+            )
+            .into()),
+            None => Err(RuntimeError::new(
+                name.line,
+                &format!("Undefined variable '{}'.", name.lexeme),
+            )
+            .into()),
         }
     }
 }
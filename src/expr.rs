@@ -1,4 +1,20 @@
-use crate::token::{LiteralKind, Token};
+use std::cell::Cell;
+
+use crate::{
+    stmt::Stmt,
+    token::{LiteralKind, Token},
+};
+
+/// Where the resolver found the variable a node refers to. Nodes that were
+/// never resolved fall back to searching the scope chain at runtime.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Resolution {
+    #[default]
+    Unresolved,
+    Global,
+    /// The number of scopes between the use and the declaration.
+    Local(usize),
+}
 
 #[derive(Debug, Clone)]
 pub enum Expr {
@@ -8,18 +24,28 @@ pub enum Expr {
     Literal(Literal),
     Logical(Logical),
     Unary(Unary),
+    Postfix(Postfix),
     Variable(Variable),
     Call(Call),
+    Lambda(Lambda),
+    List(List),
+    Map(Map),
+    Index(Index),
+    SetIndex(SetIndex),
     Get(Get),
     Set(Set),
     This(This),
     Super(Super),
+    Interpolation(Interpolation),
+    Loop(Loop),
+    Block(BlockExpression),
 }
 
 #[derive(Debug, Clone)]
 pub struct Assignment {
     pub name: Token,
     pub value: Box<Expr>,
+    pub resolution: Cell<Resolution>,
 }
 
 #[derive(Debug, Clone)]
@@ -32,11 +58,13 @@ pub struct Binary {
 #[derive(Debug, Clone)]
 pub struct Grouping {
     pub expr: Box<Expr>,
+    pub line: usize,
 }
 
 #[derive(Debug, Clone)]
 pub struct Literal {
     pub value: LiteralKind,
+    pub line: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -52,9 +80,16 @@ pub struct Unary {
     pub right: Box<Expr>,
 }
 
+#[derive(Debug, Clone)]
+pub struct Postfix {
+    pub target: Box<Expr>,
+    pub operator: Token,
+}
+
 #[derive(Debug, Clone)]
 pub struct Variable {
     pub name: Token,
+    pub resolution: Cell<Resolution>,
 }
 
 #[derive(Debug, Clone)]
@@ -64,6 +99,41 @@ pub struct Call {
     pub arguments: Vec<Expr>,
 }
 
+#[derive(Debug, Clone)]
+pub struct Lambda {
+    pub keyword: Token,
+    pub params: Vec<Token>,
+    pub variadic: bool,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone)]
+pub struct List {
+    pub bracket: Token,
+    pub elements: Vec<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Map {
+    pub brace: Token,
+    pub entries: Vec<(Expr, Expr)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Index {
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SetIndex {
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+    pub value: Box<Expr>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Get {
     pub object: Box<Expr>,
@@ -80,12 +150,40 @@ pub struct Set {
 #[derive(Debug, Clone)]
 pub struct This {
     pub keyword: Token,
+    pub resolution: Cell<Resolution>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Super {
     pub keyword: Token,
     pub method: Token,
+    pub resolution: Cell<Resolution>,
+}
+
+/// A string literal with `${expr}` holes. `parts` alternates between the
+/// literal text and the embedded expressions.
+#[derive(Debug, Clone)]
+pub struct Interpolation {
+    pub parts: Vec<Expr>,
+    pub line: usize,
+}
+
+/// `loop { ... }` runs its body until a `break`, and evaluates to the value
+/// that `break` was given, or `nil`.
+#[derive(Debug, Clone)]
+pub struct Loop {
+    pub keyword: Token,
+    pub body: Vec<Stmt>,
+}
+
+/// `{ stmt; stmt; expr }` in expression position. It runs the statements in
+/// a scope of its own and evaluates to the trailing expression, or `nil`
+/// when the block ends in a statement.
+#[derive(Debug, Clone)]
+pub struct BlockExpression {
+    pub brace: Token,
+    pub statements: Vec<Stmt>,
+    pub value: Option<Box<Expr>>,
 }
 
 pub trait ExpressionVisitor<T> {
@@ -95,15 +193,78 @@ pub trait ExpressionVisitor<T> {
     fn visit_literal(&self, expr: &Literal) -> T;
     fn visit_logical(&mut self, expr: &Logical) -> T;
     fn visit_unary(&mut self, expr: &Unary) -> T;
+    fn visit_postfix(&mut self, expr: &Postfix) -> T;
     fn visit_variable(&mut self, expr: &Variable) -> T;
     fn visit_call(&mut self, expr: &Call) -> T;
+    fn visit_lambda(&mut self, expr: &Lambda) -> T;
+    fn visit_list(&mut self, expr: &List) -> T;
+    fn visit_map(&mut self, expr: &Map) -> T;
+    fn visit_index(&mut self, expr: &Index) -> T;
+    fn visit_set_index(&mut self, expr: &SetIndex) -> T;
     fn visit_get(&mut self, expr: &Get) -> T;
     fn visit_set(&mut self, expr: &Set) -> T;
     fn visit_this(&mut self, expr: &This) -> T;
     fn visit_super(&mut self, expr: &Super) -> T;
+    fn visit_interpolation(&mut self, expr: &Interpolation) -> T;
+    fn visit_loop(&mut self, expr: &Loop) -> T;
+    fn visit_block_expression(&mut self, expr: &BlockExpression) -> T;
 }
 
 impl Expr {
+    /// The operator of a binary, unary or logical expression.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::{parser::Parser, scanner::Scanner, token::TokenKind};
+    ///
+    /// let parse = |source: &str| {
+    ///     let tokens = Scanner::new(source.to_string()).scan_tokens().clone();
+    ///     Parser::new(tokens).parse_expression().unwrap()
+    /// };
+    ///
+    /// let sum = parse("1 + 2");
+    /// let operator = sum.operator_token().unwrap();
+    /// assert_eq!(operator.kind(), TokenKind::Plus);
+    /// assert_eq!(operator.lexeme(), "+");
+    /// assert_eq!(parse("!true").operator_token().unwrap().kind(), TokenKind::Bang);
+    /// assert!(parse("1").operator_token().is_none());
+    /// ```
+    pub fn operator_token(&self) -> Option<&Token> {
+        match self {
+            Expr::Binary(binary) => Some(&binary.operator),
+            Expr::Unary(unary) => Some(&unary.operator),
+            Expr::Postfix(postfix) => Some(&postfix.operator),
+            Expr::Logical(logical) => Some(&logical.operator),
+            _ => None,
+        }
+    }
+
+    /// The line a runtime error in this expression should point at.
+    pub fn line(&self) -> usize {
+        match self {
+            Expr::Assignment(assignment) => assignment.name.line,
+            Expr::Binary(binary) => binary.operator.line,
+            Expr::Grouping(grouping) => grouping.line,
+            Expr::Literal(literal) => literal.line,
+            Expr::Logical(logical) => logical.operator.line,
+            Expr::Unary(unary) => unary.operator.line,
+            Expr::Postfix(postfix) => postfix.operator.line,
+            Expr::Variable(variable) => variable.name.line,
+            Expr::Call(call) => call.paren.line,
+            Expr::Lambda(lambda) => lambda.keyword.line,
+            Expr::List(list) => list.bracket.line,
+            Expr::Map(map) => map.brace.line,
+            Expr::Index(index) => index.bracket.line,
+            Expr::SetIndex(set_index) => set_index.bracket.line,
+            Expr::Get(get) => get.name.line,
+            Expr::Set(set) => set.name.line,
+            Expr::This(this) => this.keyword.line,
+            Expr::Super(s) => s.keyword.line,
+            Expr::Interpolation(interpolation) => interpolation.line,
+            Expr::Loop(loop_expr) => loop_expr.keyword.line,
+            Expr::Block(block) => block.brace.line,
+        }
+    }
+
     pub fn accept<T>(&self, visitor: &mut dyn ExpressionVisitor<T>) -> T {
         match self {
             Expr::Assignment(assignment) => visitor.visit_assignment(assignment),
@@ -112,12 +273,21 @@ impl Expr {
             Expr::Literal(literal) => visitor.visit_literal(literal),
             Expr::Logical(logical) => visitor.visit_logical(logical),
             Expr::Unary(unary) => visitor.visit_unary(unary),
+            Expr::Postfix(postfix) => visitor.visit_postfix(postfix),
             Expr::Variable(variable) => visitor.visit_variable(variable),
             Expr::Call(call) => visitor.visit_call(call),
+            Expr::Lambda(lambda) => visitor.visit_lambda(lambda),
+            Expr::List(list) => visitor.visit_list(list),
+            Expr::Map(map) => visitor.visit_map(map),
+            Expr::Index(index) => visitor.visit_index(index),
+            Expr::SetIndex(set_index) => visitor.visit_set_index(set_index),
             Expr::Get(get) => visitor.visit_get(get),
             Expr::Set(set) => visitor.visit_set(set),
             Expr::This(this) => visitor.visit_this(this),
             Expr::Super(s) => visitor.visit_super(s),
+            Expr::Interpolation(interpolation) => visitor.visit_interpolation(interpolation),
+            Expr::Loop(loop_expr) => visitor.visit_loop(loop_expr),
+            Expr::Block(block) => visitor.visit_block_expression(block),
         }
     }
 }
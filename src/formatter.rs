@@ -0,0 +1,516 @@
+use crate::{
+    expr::{self, Expr, ExpressionVisitor},
+    interpreter::format_number,
+    parser::{Parser, ParserError},
+    scanner::Scanner,
+    stmt::{self, StatementVisitor, Stmt},
+    token::{LiteralKind, Token},
+};
+
+const INDENT: &str = "    ";
+
+/// Re-emits a parsed program as canonically formatted Lox source: four-space
+/// indentation, spaces around binary operators and one statement per line.
+/// Comments come back verbatim above or after the statement they were
+/// attached to, and `for` loops are rebuilt from the `while` they desugar
+/// into.
+#[derive(Debug, Default)]
+pub struct Formatter {
+    indent: usize,
+}
+
+impl Formatter {
+    pub fn new() -> Self {
+        Formatter { indent: 0 }
+    }
+
+    pub fn format(&mut self, statements: &[Stmt]) -> String {
+        let mut output = String::new();
+        for statement in statements.iter() {
+            output.push_str(&self.statement(statement));
+            output.push('\n');
+        }
+        output
+    }
+
+    fn statement(&mut self, statement: &Stmt) -> String {
+        let code = statement.accept(self);
+        self.commented(statement, &code)
+    }
+
+    /// Indents `code` and puts the comments written around `statement` back.
+    fn commented(&self, statement: &Stmt, code: &str) -> String {
+        let padding = INDENT.repeat(self.indent);
+        let mut output = String::new();
+        for comment in statement.comments().leading.iter() {
+            output.push_str(&format!("{padding}{}\n", comment.trim()));
+        }
+        output.push_str(&padding);
+        output.push_str(code);
+        for comment in statement.comments().trailing.iter() {
+            output.push_str(&format!(" {}", comment.trim()));
+        }
+        output
+    }
+
+    fn block(&mut self, statements: &[Stmt]) -> String {
+        if statements.is_empty() {
+            return "{}".to_string();
+        }
+
+        self.indent += 1;
+        let body: Vec<String> = statements
+            .iter()
+            .map(|statement| self.statement(statement))
+            .collect();
+        self.indent -= 1;
+        format!("{{\n{}\n{}}}", body.join("\n"), INDENT.repeat(self.indent))
+    }
+
+    // A block body stays on the header's line; anything else goes on the
+    // next line, one level in.
+    fn body(&mut self, body: &Stmt) -> String {
+        match body {
+            Stmt::Block(block) if block.comments.is_empty() => {
+                format!(" {}", self.block(&block.statements))
+            }
+            _ => {
+                self.indent += 1;
+                let body = self.statement(body);
+                self.indent -= 1;
+                format!("\n{body}")
+            }
+        }
+    }
+
+    fn function(&mut self, params: &[Token], variadic: bool, body: &[Stmt]) -> String {
+        let mut params: Vec<String> = params
+            .iter()
+            .map(|param| param.lexeme.to_string())
+            .collect();
+        if let Some(rest) = params.last_mut().filter(|_| variadic) {
+            rest.insert_str(0, "...");
+        }
+        format!("({}) {}", params.join(", "), self.block(body))
+    }
+
+    fn list(&mut self, exprs: &[Expr]) -> String {
+        exprs
+            .iter()
+            .map(|expr| expr.accept(self))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    // `for (init; condition; increment)` parses to a block holding the
+    // initializer and a `while` that carries the increment.
+    fn for_loop(&mut self, initializer: Option<&Stmt>, stmt: &stmt::While) -> String {
+        let initializer = match initializer {
+            Some(initializer) => initializer.accept(self),
+            None => ";".to_string(),
+        };
+        let condition = stmt.condition.accept(self);
+        let increment = stmt
+            .increment
+            .as_ref()
+            .map(|increment| increment.accept(self))
+            .unwrap_or_default();
+        format!(
+            "for ({initializer} {condition}; {increment}){}",
+            self.body(&stmt.body)
+        )
+    }
+}
+
+/// Formats `source`, keeping its comments. Scan and parse errors are reported
+/// as they would be for `run`.
+///
+/// ```
+/// use codecrafters_interpreter::formatter::format_source;
+///
+/// let messy = "var x=1;if(x>0){print x;}else   print -x;\nwhile(x<3)x=x+1;";
+/// let formatted = format_source(messy).unwrap();
+/// assert_eq!(
+///     formatted,
+///     "var x = 1;\nif (x > 0) {\n    print x;\n} else\n    print -x;\nwhile (x < 3)\n    x = x + 1;\n"
+/// );
+/// assert_eq!(format_source(&formatted).unwrap(), formatted);
+/// ```
+pub fn format_source(source: &str) -> Result<String, ParserError> {
+    let (tokens, errors) = Scanner::new(source.to_string())
+        .with_comments(true)
+        .into_parts();
+    if !errors.is_empty() {
+        return Err(ParserError);
+    }
+
+    let statements = Parser::new(tokens).with_trivia(true).parse()?;
+    Ok(Formatter::new().format(&statements))
+}
+
+// Picks a spelling that scans back to the same text: raw when the text holds
+// something a plain string would decode, triple-quoted when it spans lines or
+// holds quotes.
+fn string_literal(text: &str) -> String {
+    let raw = text.contains("${") || text.contains("\\u{");
+    let quotes = match text.contains('"') || text.contains('\n') {
+        true => "\"\"\"",
+        false => "\"",
+    };
+    let leading_newline = match quotes.len() == 3 && text.starts_with('\n') {
+        true => "\n",
+        false => "",
+    };
+    format!(
+        "{}{quotes}{leading_newline}{text}{quotes}",
+        if raw { "r" } else { "" }
+    )
+}
+
+fn literal(value: &LiteralKind) -> String {
+    match value {
+        LiteralKind::String(string) => string_literal(string),
+        LiteralKind::Number(number) => format_number(*number),
+        value => String::from(value.clone()),
+    }
+}
+
+impl ExpressionVisitor<String> for Formatter {
+    fn visit_assignment(&mut self, expr: &expr::Assignment) -> String {
+        format!("{} = {}", expr.name.lexeme, expr.value.accept(self))
+    }
+
+    fn visit_binary(&mut self, expr: &expr::Binary) -> String {
+        format!(
+            "{} {} {}",
+            expr.left.accept(self),
+            expr.operator.lexeme,
+            expr.right.accept(self)
+        )
+    }
+
+    fn visit_grouping(&mut self, expr: &expr::Grouping) -> String {
+        format!("({})", expr.expr.accept(self))
+    }
+
+    fn visit_literal(&self, expr: &expr::Literal) -> String {
+        literal(&expr.value)
+    }
+
+    fn visit_logical(&mut self, expr: &expr::Logical) -> String {
+        format!(
+            "{} {} {}",
+            expr.left.accept(self),
+            expr.operator.lexeme,
+            expr.right.accept(self)
+        )
+    }
+
+    fn visit_unary(&mut self, expr: &expr::Unary) -> String {
+        let right = expr.right.accept(self);
+        // `- -x` must not run together into the `--` operator.
+        match expr.operator.lexeme() == "-" && right.starts_with('-') {
+            true => format!("- {right}"),
+            false => format!("{}{right}", expr.operator.lexeme),
+        }
+    }
+
+    fn visit_postfix(&mut self, expr: &expr::Postfix) -> String {
+        format!("{}{}", expr.target.accept(self), expr.operator.lexeme)
+    }
+
+    fn visit_variable(&mut self, expr: &expr::Variable) -> String {
+        expr.name.lexeme.to_string()
+    }
+
+    fn visit_call(&mut self, expr: &expr::Call) -> String {
+        format!(
+            "{}({})",
+            expr.callee.accept(self),
+            self.list(&expr.arguments)
+        )
+    }
+
+    fn visit_lambda(&mut self, expr: &expr::Lambda) -> String {
+        format!(
+            "fun {}",
+            self.function(&expr.params, expr.variadic, &expr.body)
+        )
+    }
+
+    fn visit_list(&mut self, expr: &expr::List) -> String {
+        format!("[{}]", self.list(&expr.elements))
+    }
+
+    fn visit_map(&mut self, expr: &expr::Map) -> String {
+        let entries: Vec<String> = expr
+            .entries
+            .iter()
+            .map(|(key, value)| format!("{}: {}", key.accept(self), value.accept(self)))
+            .collect();
+        format!("{{{}}}", entries.join(", "))
+    }
+
+    fn visit_index(&mut self, expr: &expr::Index) -> String {
+        format!("{}[{}]", expr.object.accept(self), expr.index.accept(self))
+    }
+
+    fn visit_set_index(&mut self, expr: &expr::SetIndex) -> String {
+        format!(
+            "{}[{}] = {}",
+            expr.object.accept(self),
+            expr.index.accept(self),
+            expr.value.accept(self)
+        )
+    }
+
+    fn visit_get(&mut self, expr: &expr::Get) -> String {
+        format!("{}.{}", expr.object.accept(self), expr.name.lexeme)
+    }
+
+    fn visit_set(&mut self, expr: &expr::Set) -> String {
+        format!(
+            "{}.{} = {}",
+            expr.object.accept(self),
+            expr.name.lexeme,
+            expr.value.accept(self)
+        )
+    }
+
+    fn visit_this(&mut self, _expr: &expr::This) -> String {
+        "this".to_string()
+    }
+
+    fn visit_super(&mut self, expr: &expr::Super) -> String {
+        format!("super.{}", expr.method.lexeme)
+    }
+
+    fn visit_loop(&mut self, expr: &expr::Loop) -> String {
+        format!("loop {}", self.block(&expr.body))
+    }
+
+    fn visit_block_expression(&mut self, expr: &expr::BlockExpression) -> String {
+        let Some(value) = &expr.value else {
+            return self.block(&expr.statements);
+        };
+
+        self.indent += 1;
+        let mut body: Vec<String> = expr
+            .statements
+            .iter()
+            .map(|statement| self.statement(statement))
+            .collect();
+        body.push(format!(
+            "{}{}",
+            INDENT.repeat(self.indent),
+            value.accept(self)
+        ));
+        self.indent -= 1;
+        format!("{{\n{}\n{}}}", body.join("\n"), INDENT.repeat(self.indent))
+    }
+
+    fn visit_interpolation(&mut self, expr: &expr::Interpolation) -> String {
+        let mut text = String::new();
+        let mut triple = false;
+        for (i, part) in expr.parts.iter().enumerate() {
+            match (i % 2, part) {
+                (
+                    0,
+                    Expr::Literal(expr::Literal {
+                        value: LiteralKind::String(string),
+                        ..
+                    }),
+                ) => {
+                    triple |= string.contains('"') || string.contains('\n');
+                    text.push_str(string);
+                }
+                _ => text.push_str(&format!("${{{}}}", part.accept(self))),
+            }
+        }
+
+        match triple {
+            true => format!("\"\"\"{text}\"\"\""),
+            false => format!("\"{text}\""),
+        }
+    }
+}
+
+impl StatementVisitor<String> for Formatter {
+    fn visit_expression(&mut self, stmt: &stmt::Expression) -> String {
+        format!("{};", stmt.expression.accept(self))
+    }
+
+    fn visit_print(&mut self, stmt: &stmt::Print) -> String {
+        format!("print {};", self.list(&stmt.expressions))
+    }
+
+    fn visit_var(&mut self, stmt: &stmt::Var) -> String {
+        let keyword = if stmt.constant { "const" } else { "var" };
+        match &stmt.initializer {
+            Some(initializer) => {
+                format!(
+                    "{} {} = {};",
+                    keyword,
+                    stmt.name.lexeme,
+                    initializer.accept(self)
+                )
+            }
+            None => format!("{} {};", keyword, stmt.name.lexeme),
+        }
+    }
+
+    fn visit_block(&mut self, stmt: &stmt::Block) -> String {
+        if let [initializer @ (Stmt::Var(_) | Stmt::Expression(_)), Stmt::While(body)] =
+            stmt.statements.as_slice()
+        {
+            if body.increment.is_some() && body.comments.is_empty() {
+                return self.for_loop(Some(initializer), body);
+            }
+        }
+        self.block(&stmt.statements)
+    }
+
+    /// An `if` in the else branch continues the chain on the `else` line
+    /// instead of nesting a level deeper.
+    fn visit_if(&mut self, stmt: &stmt::If) -> String {
+        let mut output = format!(
+            "if ({}){}",
+            stmt.condition.accept(self),
+            self.body(&stmt.then_branch)
+        );
+        if let Some(else_branch) = &stmt.else_branch {
+            match stmt.then_branch.as_ref() {
+                Stmt::Block(block) if block.comments.is_empty() => output.push_str(" else"),
+                _ => output.push_str(&format!("\n{}else", INDENT.repeat(self.indent))),
+            }
+            match else_branch.as_ref() {
+                Stmt::If(else_if) if else_if.comments.is_empty() => {
+                    output.push(' ');
+                    output.push_str(&self.visit_if(else_if));
+                }
+                else_branch => output.push_str(&self.body(else_branch)),
+            }
+        }
+        output
+    }
+
+    fn visit_while(&mut self, stmt: &stmt::While) -> String {
+        if stmt.increment.is_some() {
+            return self.for_loop(None, stmt);
+        }
+        format!(
+            "while ({}){}",
+            stmt.condition.accept(self),
+            self.body(&stmt.body)
+        )
+    }
+
+    fn visit_for_in(&mut self, stmt: &stmt::ForIn) -> String {
+        format!(
+            "for ({} in {}){}",
+            stmt.variable.lexeme,
+            stmt.iterable.accept(self),
+            self.body(&stmt.body)
+        )
+    }
+
+    fn visit_function(&mut self, stmt: &stmt::Function) -> String {
+        match stmt.getter {
+            true => format!("{} {}", stmt.name.lexeme, self.block(&stmt.body)),
+            false => format!(
+                "fun {}{}",
+                stmt.name.lexeme,
+                self.function(&stmt.params, stmt.variadic, &stmt.body)
+            ),
+        }
+    }
+
+    fn visit_return(&mut self, stmt: &stmt::Return) -> String {
+        match &stmt.value {
+            Some(value) => format!("return {};", value.accept(self)),
+            None => "return;".to_string(),
+        }
+    }
+
+    fn visit_class(&mut self, stmt: &stmt::Class) -> String {
+        let mut output = format!("class {}", stmt.name.lexeme);
+        if let Some(super_class) = &stmt.super_class {
+            output.push_str(&format!(" < {}", super_class.accept(self)));
+        }
+        if stmt.methods.is_empty() && stmt.class_methods.is_empty() {
+            output.push_str(" {}");
+            return output;
+        }
+
+        self.indent += 1;
+        let mut members: Vec<(&str, &Stmt)> = stmt
+            .class_methods
+            .iter()
+            .map(|method| ("class ", method))
+            .chain(stmt.methods.iter().map(|method| ("", method)))
+            .collect();
+        // Class methods are kept apart from the others, so put the two back
+        // in the order they were written.
+        members.sort_by_key(|(_, method)| method.line());
+        let mut methods = Vec::new();
+        for (prefix, member) in members {
+            let Stmt::Function(method) = member else {
+                continue;
+            };
+            let signature = match method.getter {
+                true => format!("{} {}", method.name.lexeme, self.block(&method.body)),
+                false => format!(
+                    "{}{}",
+                    method.name.lexeme,
+                    self.function(&method.params, method.variadic, &method.body)
+                ),
+            };
+            methods.push(self.commented(member, &format!("{prefix}{signature}")));
+        }
+        self.indent -= 1;
+
+        output.push_str(&format!(
+            " {{\n{}\n{}}}",
+            methods.join("\n"),
+            INDENT.repeat(self.indent)
+        ));
+        output
+    }
+
+    fn visit_switch(&mut self, stmt: &stmt::Switch) -> String {
+        let padding = INDENT.repeat(self.indent + 1);
+        let mut output = format!("switch ({}) {{\n", stmt.scrutinee.accept(self));
+        let arms = stmt
+            .arms
+            .iter()
+            .map(|(patterns, body)| (Some(patterns), body));
+        let default = stmt.default.iter().map(|body| (None, body));
+        for (patterns, body) in arms.chain(default) {
+            let label = match patterns {
+                Some(patterns) => format!("case {}:", self.list(patterns)),
+                None => "default:".to_string(),
+            };
+            output.push_str(&format!("{padding}{label}\n"));
+            self.indent += 2;
+            for statement in body.iter() {
+                output.push_str(&self.statement(statement));
+                output.push('\n');
+            }
+            self.indent -= 2;
+        }
+        output.push_str(&INDENT.repeat(self.indent));
+        output.push('}');
+        output
+    }
+
+    fn visit_break(&mut self, stmt: &stmt::Break) -> String {
+        match &stmt.value {
+            Some(value) => format!("break {};", value.accept(self)),
+            None => "break;".to_string(),
+        }
+    }
+
+    fn visit_continue(&mut self, _stmt: &stmt::Continue) -> String {
+        "continue;".to_string()
+    }
+}
@@ -0,0 +1,67 @@
+use std::ops::Range;
+
+use crate::{scanner::Scanner, token::TokenKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenClass {
+    Keyword,
+    Operator,
+    Number,
+    String,
+    Identifier,
+    Comment,
+    Punctuation,
+}
+
+impl From<TokenKind> for TokenClass {
+    fn from(kind: TokenKind) -> Self {
+        use TokenKind::*;
+        match kind {
+            LeftParenthesis | RightParenthesis | LeftBrace | RightBrace | LeftBracket
+            | RightBracket | Comma | Colon | Dot | Semicolon | DotDotDot | EOF => {
+                TokenClass::Punctuation
+            }
+            Minus | Plus | Slash | Star | Question | Bang | BangEqual | Equal | EqualEqual
+            | Greater | GreaterEqual | Less | LessEqual | MinusMinus | PlusPlus => {
+                TokenClass::Operator
+            }
+            Identifier => TokenClass::Identifier,
+            String | Interpolation => TokenClass::String,
+            Number => TokenClass::Number,
+            Comment => TokenClass::Comment,
+            And | Break | Case | Class | Const | Continue | Default | Else | False | Fun | For
+            | If | In | Is | Loop | Nil | Or | Print | Return | Super | Switch | This | True
+            | Var | While | Xor => TokenClass::Keyword,
+        }
+    }
+}
+
+/// Splits `source` into byte ranges tagged for syntax highlighting. Text that
+/// doesn't scan, like an unterminated string, is left out rather than
+/// reported.
+///
+/// ```
+/// use codecrafters_interpreter::highlight::{classify_tokens, TokenClass};
+///
+/// let source = "print \"hi\"; // greet";
+/// let classes = classify_tokens(source);
+/// assert_eq!(
+///     classes,
+///     vec![
+///         (0..5, TokenClass::Keyword),
+///         (6..10, TokenClass::String),
+///         (10..11, TokenClass::Punctuation),
+///         (12..20, TokenClass::Comment),
+///     ]
+/// );
+/// assert_eq!(&source[12..20], "// greet");
+/// ```
+pub fn classify_tokens(source: &str) -> Vec<(Range<usize>, TokenClass)> {
+    Scanner::new(source.to_string())
+        .with_comments(true)
+        .into_spanned_tokens()
+        .into_iter()
+        .filter(|(token, _)| token.kind() != TokenKind::EOF)
+        .map(|(token, span)| (span, TokenClass::from(token.kind())))
+        .collect()
+}
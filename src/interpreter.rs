@@ -1,106 +1,891 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::Display,
+    io::{self, BufRead, Write},
+    rc::Rc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use thiserror::Error;
 
 use crate::{
+    callable::{Class, Function, Instance, NativeFunction},
     environement::Environment,
-    expr::{self, Expr, ExpressionVisitor, Literal},
-    report,
+    expr::{self, Expr, ExpressionVisitor, Resolution},
+    formatter::Formatter,
+    natives::define_natives,
     stmt::{self, StatementVisitor, Stmt},
-    token::{LiteralKind, TokenKind},
+    token::{LiteralKind, Token, TokenKind},
 };
 
+#[derive(Debug, Clone, Error)]
+#[error("[line {line}] Error: {message}")]
+pub struct RuntimeError {
+    pub message: String,
+    pub line: usize,
+    /// The calls that were active when the error was raised, innermost first.
+    pub trace: Vec<Frame>,
+}
+
+impl RuntimeError {
+    pub fn new(line: usize, message: &str) -> Self {
+        RuntimeError {
+            message: message.to_string(),
+            line,
+            trace: Vec::new(),
+        }
+    }
+}
+
+/// A call to a Lox function or class, recorded with the line it was made on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub function: String,
+    pub line: usize,
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at {} (line {})", self.function, self.line)
+    }
+}
+
+/// A value produced while running a program. Literal values come straight
+/// from `LiteralKind`; everything else only exists at runtime.
+///
+/// ```
+/// use codecrafters_interpreter::{
+///     interpreter::{Interpreter, Value},
+///     parser::Parser,
+///     scanner::Scanner,
+///     token::LiteralKind,
+/// };
+///
+/// assert_eq!(Value::from(LiteralKind::Number(1.5)), Value::Number(1.5));
+/// assert_eq!(Value::from(LiteralKind::Nil), Value::Nil);
+///
+/// let (tokens, _) = Scanner::new("1 + 1".to_string()).into_parts();
+/// let expr = Parser::new(tokens).parse_expression().unwrap();
+/// assert_eq!(Interpreter::new().evaluate_expression(&expr).unwrap(), Value::Number(2.0));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Nil,
+    Function(Function),
+    NativeFunction(NativeFunction),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<HashMap<String, Value>>>),
+    Class(Rc<Class>),
+    Instance(Rc<RefCell<Instance>>),
+}
+
+// The plain rendering, without calling `toString`. Error messages use it
+// directly; `print` goes through `Interpreter::stringify`.
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_on_path(f, &mut Vec::new())
+    }
+}
+
+impl Value {
+    /// Writes the value, where `path` holds the lists and maps being written
+    /// further out. One that shows up inside itself is written as `[...]` or
+    /// `{...}`.
+    fn fmt_on_path(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        path: &mut Vec<*const ()>,
+    ) -> std::fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Number(number) => write!(f, "{}", format_number(*number)),
+            Value::String(string) => write!(f, "{string}"),
+            Value::Bool(bool) => write!(f, "{bool}"),
+            Value::Function(function) => write!(f, "{function}"),
+            Value::NativeFunction(_) => write!(f, "<native fn>"),
+            Value::List(list) => {
+                let id = Rc::as_ptr(list).cast::<()>();
+                if path.contains(&id) {
+                    return write!(f, "[...]");
+                }
+                path.push(id);
+                write!(f, "[")?;
+                for (index, element) in list.borrow().iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.fmt_on_path(f, path)?;
+                }
+                path.pop();
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                let id = Rc::as_ptr(map).cast::<()>();
+                if path.contains(&id) {
+                    return write!(f, "{{...}}");
+                }
+                path.push(id);
+                let map = map.borrow();
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                write!(f, "{{")?;
+                for (index, key) in keys.into_iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{key}: ")?;
+                    map[key].fmt_on_path(f, path)?;
+                }
+                path.pop();
+                write!(f, "}}")
+            }
+            Value::Class(class) => write!(f, "{class}"),
+            Value::Instance(instance) => write!(f, "{}", instance.borrow()),
+        }
+    }
+}
+
+impl From<LiteralKind> for Value {
+    fn from(literal: LiteralKind) -> Self {
+        match literal {
+            LiteralKind::String(string) => Value::String(string),
+            LiteralKind::Number(number) => Value::Number(number),
+            LiteralKind::Bool(bool) => Value::Bool(bool),
+            LiteralKind::Nil => Value::Nil,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
 pub enum Exit {
-    RuntimeError,
-    Return(LiteralKind),
+    #[error(transparent)]
+    RuntimeError(#[from] RuntimeError),
+    #[error("Unexpected 'return' outside of a function.")]
+    Return(Value),
+    #[error("Unexpected 'break' outside of a loop.")]
+    Break(Value),
+    #[error("Unexpected 'continue' outside of a loop.")]
+    Continue,
+}
+
+/// Time spent in one function, including the calls it made, and how often
+/// it was called.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Profile {
+    pub time: Duration,
+    pub calls: usize,
+}
+
+// Blocks take the line of their first statement, so only what they contain
+// is counted.
+fn statement_lines(statements: &[Stmt], lines: &mut BTreeSet<usize>) {
+    for statement in statements.iter() {
+        if !matches!(statement, Stmt::Block(_)) {
+            lines.insert(statement.line());
+        }
+        match statement {
+            Stmt::Block(stmt) => statement_lines(&stmt.statements, lines),
+            Stmt::If(stmt) => {
+                statement_lines(std::slice::from_ref(&stmt.then_branch), lines);
+                if let Some(else_branch) = &stmt.else_branch {
+                    statement_lines(std::slice::from_ref(else_branch), lines);
+                }
+            }
+            Stmt::While(stmt) => statement_lines(std::slice::from_ref(&stmt.body), lines),
+            Stmt::ForIn(stmt) => statement_lines(std::slice::from_ref(&stmt.body), lines),
+            Stmt::Function(stmt) => statement_lines(&stmt.body, lines),
+            Stmt::Class(stmt) => {
+                statement_lines(&stmt.methods, lines);
+                statement_lines(&stmt.class_methods, lines);
+            }
+            Stmt::Switch(stmt) => {
+                for (_, body) in stmt.arms.iter() {
+                    statement_lines(body, lines);
+                }
+                if let Some(default) = &stmt.default {
+                    statement_lines(default, lines);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Formats a number the way `print` shows it. `f64`'s `Display` already
+/// prints integral values without a fraction (`4`), never switches to
+/// scientific notation (`1e21` prints all 22 digits) and otherwise gives the
+/// shortest text that parses back to the same value (`0.30000000000000004`).
+pub(crate) fn format_number(number: f64) -> String {
+    number.to_string()
 }
 
 pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
     environment: Rc<RefCell<Environment>>,
+    ieee_division: bool,
+    // `None` reads from stdin through std's shared buffer, so input is not
+    // swallowed from under anything else reading stdin, like the REPL.
+    input: Option<Box<dyn BufRead>>,
+    output: Box<dyn Write>,
+    trace: Option<Box<dyn Write>>,
+    profile: Option<HashMap<String, Profile>>,
+    coverage: Option<HashSet<usize>>,
+    call_stack: Vec<Frame>,
+    max_call_depth: usize,
+    random_state: u64,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
-        Self {
-            environment: Rc::new(RefCell::new(Environment::new())),
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        let interpreter = Self {
+            environment: Rc::clone(&globals),
+            globals,
+            ieee_division: false,
+            input: None,
+            output: Box::new(io::stdout()),
+            trace: None,
+            profile: None,
+            coverage: None,
+            call_stack: Vec::new(),
+            max_call_depth: 512,
+            random_state: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_nanos() as u64),
+        };
+        define_natives(&mut interpreter.globals.borrow_mut());
+        interpreter
+    }
+
+    pub fn reset(&mut self) {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        define_natives(&mut globals.borrow_mut());
+        self.environment = Rc::clone(&globals);
+        self.globals = globals;
+        self.call_stack.clear();
+    }
+
+    pub fn with_ieee_division(mut self, ieee_division: bool) -> Self {
+        self.ieee_division = ieee_division;
+        self
+    }
+
+    /// Limits how many Lox calls may be active at once. Going deeper raises
+    /// "Stack overflow." instead of overflowing the Rust stack.
+    ///
+    /// How much Rust stack a Lox call takes depends on how deeply its body
+    /// nests, and debug builds take several times more. The default of 512
+    /// fits a main thread's stack for simple recursion only, so run the
+    /// interpreter on a thread with a larger stack, as the CLI does, or lower
+    /// the limit.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::{interpreter::Interpreter, parser::Parser, scanner::Scanner};
+    ///
+    /// let source = "fun f() {\n  return f();\n}\nf();";
+    /// let (tokens, _) = Scanner::new(source.to_string()).into_parts();
+    /// let statements = Parser::new(tokens).parse().unwrap();
+    ///
+    /// let error = Interpreter::new().interpret(&statements).unwrap_err();
+    /// assert_eq!(error.message, "Stack overflow.");
+    /// assert_eq!(error.line, 2);
+    /// assert_eq!(error.trace.len(), 512);
+    ///
+    /// let mut interpreter = Interpreter::new().with_max_call_depth(8);
+    /// let error = interpreter.interpret(&statements).unwrap_err();
+    /// assert_eq!(error.trace.len(), 8);
+    /// ```
+    pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.max_call_depth = max_call_depth;
+        self
+    }
+
+    /// Seeds `random` and `randint` so a run draws a repeatable sequence.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed(seed);
+        self
+    }
+
+    pub(crate) fn seed(&mut self, seed: u64) {
+        self.random_state = seed;
+    }
+
+    /// Draws a number in `[0, 1)` using SplitMix64.
+    pub(crate) fn next_random(&mut self) -> f64 {
+        self.random_state = self.random_state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.random_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Replaces stdin as the source `read_line` reads from.
+    pub fn with_input(mut self, input: impl BufRead + 'static) -> Self {
+        self.input = Some(Box::new(input));
+        self
+    }
+
+    /// Replaces stdout as the sink `print` and `write` send their text to.
+    pub fn with_output(mut self, output: impl Write + 'static) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Logs every statement to `trace` just before it runs, as its line and
+    /// the first line of its source.
+    ///
+    /// ```
+    /// use std::{cell::RefCell, io::Write, rc::Rc};
+    ///
+    /// use codecrafters_interpreter::{interpreter::Interpreter, parser::Parser, scanner::Scanner};
+    ///
+    /// #[derive(Clone, Default)]
+    /// struct Output(Rc<RefCell<Vec<u8>>>);
+    ///
+    /// impl Write for Output {
+    ///     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    ///         self.0.borrow_mut().write(buf)
+    ///     }
+    ///
+    ///     fn flush(&mut self) -> std::io::Result<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let (tokens, _) = Scanner::new("var a = 1;\nprint a+1;".to_string()).into_parts();
+    /// let trace = Output::default();
+    /// let mut interpreter = Interpreter::new()
+    ///     .with_output(Output::default())
+    ///     .with_trace(trace.clone());
+    /// interpreter.interpret(&Parser::new(tokens).parse().unwrap()).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(trace.0.borrow().clone()).unwrap(),
+    ///     "[line 1] var a = 1;\n[line 2] print a + 1;\n"
+    /// );
+    /// ```
+    pub fn with_trace(mut self, trace: impl Write + 'static) -> Self {
+        self.trace = Some(Box::new(trace));
+        self
+    }
+
+    /// Records how long each function and class call takes, for
+    /// `profile_report`.
+    pub fn with_profiling(mut self, profiling: bool) -> Self {
+        self.profile = profiling.then(HashMap::new);
+        self
+    }
+
+    /// One line per function called so far, slowest first, like
+    /// `add: 12ms (3 calls)`. `None` unless profiling is on.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::{interpreter::Interpreter, parser::Parser, scanner::Scanner};
+    ///
+    /// let source = "fun add(a, b) { return a + b; } for (var i = 0; i < 5; i = i + 1) add(i, i);";
+    /// let (tokens, _) = Scanner::new(source.to_string()).into_parts();
+    /// let mut interpreter = Interpreter::new().with_profiling(true);
+    /// interpreter.interpret(&Parser::new(tokens).parse().unwrap()).unwrap();
+    ///
+    /// let report = interpreter.profile_report().unwrap();
+    /// assert!(report.starts_with("add: "));
+    /// assert!(report.ends_with("ms (5 calls)\n"));
+    /// assert!(Interpreter::new().profile_report().is_none());
+    /// ```
+    pub fn profile_report(&self) -> Option<String> {
+        let mut entries: Vec<(&String, &Profile)> = self.profile.as_ref()?.iter().collect();
+        entries.sort_by(|(a, a_profile), (b, b_profile)| {
+            b_profile.time.cmp(&a_profile.time).then(a.cmp(b))
+        });
+        let report = entries
+            .into_iter()
+            .map(|(name, profile)| {
+                format!(
+                    "{}: {}ms ({} {})\n",
+                    name,
+                    profile.time.as_millis(),
+                    profile.calls,
+                    if profile.calls == 1 { "call" } else { "calls" }
+                )
+            })
+            .collect();
+        Some(report)
+    }
+
+    /// Records the line of every statement that runs, for `executed_lines`
+    /// and `uncovered_lines`.
+    pub fn with_coverage(mut self, coverage: bool) -> Self {
+        self.coverage = coverage.then(HashSet::new);
+        self
+    }
+
+    pub fn executed_lines(&self) -> HashSet<usize> {
+        self.coverage.clone().unwrap_or_default()
+    }
+
+    /// The lines of statements in `statements`, at any depth, that haven't
+    /// run yet.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::{interpreter::Interpreter, parser::Parser, scanner::Scanner};
+    ///
+    /// let source = "var a = 1;\nif (a > 0)\n    print a;\nelse\n    print -a;\n";
+    /// let (tokens, _) = Scanner::new(source.to_string()).into_parts();
+    /// let statements = Parser::new(tokens).parse().unwrap();
+    /// let mut interpreter = Interpreter::new().with_coverage(true);
+    /// interpreter.interpret(&statements).unwrap();
+    ///
+    /// assert!(interpreter.executed_lines().contains(&3));
+    /// assert_eq!(interpreter.uncovered_lines(&statements), vec![5]);
+    /// ```
+    pub fn uncovered_lines(&self, statements: &[Stmt]) -> Vec<usize> {
+        let mut lines = BTreeSet::new();
+        statement_lines(statements, &mut lines);
+        let executed = self.executed_lines();
+        lines
+            .into_iter()
+            .filter(|line| !executed.contains(line))
+            .collect()
+    }
+
+    pub(crate) fn write_output(&mut self, line: usize, text: &str) -> Result<(), Exit> {
+        self.output
+            .write_all(text.as_bytes())
+            .and_then(|()| self.output.flush())
+            .map_err(|_| RuntimeError::new(line, "Failed to write output.").into())
+    }
+
+    pub(crate) fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        let read = match &mut self.input {
+            Some(input) => input.read_line(&mut line)?,
+            None => io::stdin().read_line(&mut line)?,
+        };
+        if read == 0 {
+            return Ok(None);
+        }
+
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
         }
+        Ok(Some(line))
     }
 
-    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), Exit> {
-        let mut has_error = false;
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), RuntimeError> {
         for statement in statements.iter() {
-            match self.execute(statement) {
-                Ok(_) => (),
-                Err(e) => {
-                    if let Exit::RuntimeError = e {
-                        has_error = true;
-                        break;
-                    }
-                }
-            }
+            self.interpret_statement(statement)?;
         }
 
-        match has_error {
-            true => Err(Exit::RuntimeError),
-            false => Ok(()),
+        Ok(())
+    }
+
+    /// Runs a single top-level statement, for drivers that execute a program
+    /// as it is parsed. The parser keeps `return`, `break` and `continue`
+    /// where they belong, but a statement built by hand that lets one escape
+    /// is reported rather than ignored.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::{
+    ///     interpreter::Interpreter, parser::Parser, scanner::Scanner, stmt::Stmt,
+    /// };
+    ///
+    /// let (tokens, _) = Scanner::new("fun f() { return 1; }".to_string()).into_parts();
+    /// let Stmt::Function(function) = &Parser::new(tokens).parse().unwrap()[0] else {
+    ///     unreachable!()
+    /// };
+    ///
+    /// let error = Interpreter::new()
+    ///     .interpret_statement(&function.body[0])
+    ///     .unwrap_err();
+    /// assert_eq!(error.message, "Unexpected 'return' outside of a function.");
+    /// assert_eq!(error.line, 1);
+    /// ```
+    pub fn interpret_statement(&mut self, statement: &Stmt) -> Result<(), RuntimeError> {
+        self.execute(statement)
+            .map_err(|exit| Self::escaped(exit, statement.line()))
+    }
+
+    fn escaped(exit: Exit, line: usize) -> RuntimeError {
+        match exit {
+            Exit::RuntimeError(error) => error,
+            exit => RuntimeError::new(line, &exit.to_string()),
         }
     }
 
-    pub fn interpret_expression(&mut self, expr: &Expr) -> Result<String, Exit> {
-        match self.evaluate(&expr) {
-            Ok(literal) => Ok(self.stringify(literal)),
-            Err(exit) => match exit {
-                Exit::RuntimeError => Err(Exit::RuntimeError),
-                Exit::Return(_literal_kind) => todo!(),
-            },
+    /// Like `interpret`, but echoes the value of each top-level expression
+    /// statement the way a REPL does. Assignments and declarations stay quiet.
+    pub fn interpret_repl(&mut self, statements: &[Stmt]) -> Result<(), RuntimeError> {
+        for statement in statements.iter() {
+            let result = match statement {
+                Stmt::Expression(stmt)
+                    if !matches!(
+                        *stmt.expression,
+                        Expr::Assignment(_) | Expr::SetIndex(_) | Expr::Set(_) | Expr::Postfix(_)
+                    ) =>
+                {
+                    let line = stmt.expression.line();
+                    self.evaluate(&stmt.expression)
+                        .and_then(|value| self.stringify(value, line))
+                        .and_then(|text| self.write_output(line, &format!("{text}\n")))
+                }
+                _ => self.execute(statement),
+            };
+
+            if let Err(exit) = result {
+                return Err(Self::escaped(exit, statement.line()));
+            }
         }
+
+        Ok(())
+    }
+
+    pub fn interpret_expression(&mut self, expr: &Expr) -> Result<String, RuntimeError> {
+        let value = self.evaluate_expression(expr)?;
+        self.stringify(value, expr.line())
+            .map_err(|exit| Self::escaped(exit, expr.line()))
+    }
+
+    pub fn evaluate_expression(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        self.evaluate(expr)
+            .map_err(|exit| Self::escaped(exit, expr.line()))
     }
 
     fn execute(&mut self, stmt: &Stmt) -> Result<(), Exit> {
+        self.record_statement(stmt);
         stmt.accept(self)
     }
 
-    fn stringify(&self, literal: LiteralKind) -> String {
-        match literal {
-            LiteralKind::Nil => "nil".to_string(),
-            LiteralKind::Number(num) => {
-                let mut text = num.to_string();
-                if text.ends_with(".0") {
-                    text = text[0..text.len() - 2].to_string();
+    // Feeds the trace and coverage, when they're on, just before a
+    // statement runs.
+    fn record_statement(&mut self, stmt: &Stmt) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.insert(stmt.line());
+        }
+        if let Some(trace) = &mut self.trace {
+            let source = Formatter::new().format(std::slice::from_ref(stmt));
+            let source = source.lines().next().unwrap_or_default();
+            // The trace is a debugging aid, so failing to write it doesn't
+            // stop the program.
+            let _ = writeln!(trace, "[line {}] {}", stmt.line(), source);
+        }
+    }
+
+    /// Renders a value the way `print` shows it. An instance whose class
+    /// defines `toString` is shown as whatever that method returns, which must
+    /// be a string; `line` is where the text is being produced.
+    pub(crate) fn stringify(&mut self, value: Value, line: usize) -> Result<String, Exit> {
+        self.stringify_on_path(value, line, &mut Vec::new())
+    }
+
+    // Like `Display for Value`, `path` holds the lists and maps being
+    // stringified further out so one that contains itself comes out as
+    // `[...]` or `{...}`.
+    fn stringify_on_path(
+        &mut self,
+        value: Value,
+        line: usize,
+        path: &mut Vec<*const ()>,
+    ) -> Result<String, Exit> {
+        match value {
+            Value::List(list) => {
+                let id = Rc::as_ptr(&list).cast::<()>();
+                if path.contains(&id) {
+                    return Ok("[...]".to_string());
                 }
-                text
+                path.push(id);
+                let elements = list.borrow().clone();
+                let elements = elements
+                    .into_iter()
+                    .map(|element| self.stringify_on_path(element, line, path))
+                    .collect::<Result<Vec<String>, Exit>>();
+                path.pop();
+                Ok(format!("[{}]", elements?.join(", ")))
             }
-            LiteralKind::String(s) => s.to_string(),
-            LiteralKind::Bool(b) => b.to_string(),
+            Value::Map(map) => {
+                let id = Rc::as_ptr(&map).cast::<()>();
+                if path.contains(&id) {
+                    return Ok("{...}".to_string());
+                }
+                path.push(id);
+                let mut entries: Vec<(String, Value)> = map.borrow().clone().into_iter().collect();
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                let entries = entries
+                    .into_iter()
+                    .map(|(key, value)| {
+                        Ok(format!(
+                            "{}: {}",
+                            key,
+                            self.stringify_on_path(value, line, path)?
+                        ))
+                    })
+                    .collect::<Result<Vec<String>, Exit>>();
+                path.pop();
+                Ok(format!("{{{}}}", entries?.join(", ")))
+            }
+            Value::Instance(ref instance) => {
+                let Some(method) = instance.borrow().class.find_method("toString") else {
+                    return Ok(value.to_string());
+                };
+                let name = Token::new(
+                    TokenKind::Identifier,
+                    "toString".to_string(),
+                    LiteralKind::Nil,
+                    line,
+                );
+                match self.call(Value::Function(method.bind(value.clone())), &name, vec![])? {
+                    Value::String(string) => Ok(string),
+                    other => Err(RuntimeError::new(
+                        line,
+                        &format!(
+                            "toString must return a string, got {}.",
+                            self.describe(&other)
+                        ),
+                    )
+                    .into()),
+                }
+            }
+            value => Ok(value.to_string()),
+        }
+    }
+
+    pub(crate) fn describe(&self, value: &Value) -> String {
+        match value {
+            Value::String(string) => format!("\"{string}\""),
+            _ => value.to_string(),
         }
     }
 
-    fn evaluate(&mut self, expr: &expr::Expr) -> Result<LiteralKind, Exit> {
+    /// Lets a class implement `+`, `-`, `*`, `/` and `==` with `add`, `sub`,
+    /// `mul`, `div` and `equals` methods, which are looked up on the left
+    /// operand and called with the right one. `!=` negates `equals`. Without
+    /// such a method the operator behaves as usual.
+    fn overloaded_operator(
+        &mut self,
+        operator: &Token,
+        left: &Value,
+        right: &Value,
+    ) -> Result<Option<Value>, Exit> {
+        let name = match operator.kind {
+            TokenKind::Plus => "add",
+            TokenKind::Minus => "sub",
+            TokenKind::Star => "mul",
+            TokenKind::Slash => "div",
+            TokenKind::EqualEqual | TokenKind::BangEqual => "equals",
+            _ => return Ok(None),
+        };
+        let Value::Instance(instance) = left else {
+            return Ok(None);
+        };
+        let Some(method) = instance.borrow().class.find_method(name) else {
+            return Ok(None);
+        };
+
+        let method = Value::Function(method.bind(left.clone()));
+        let result = self.call(method, operator, vec![right.clone()])?;
+        match operator.kind {
+            TokenKind::BangEqual => Ok(Some(Value::Bool(!self.is_truthy(&result)))),
+            _ => Ok(Some(result)),
+        }
+    }
+
+    fn operands_error(&self, operator: &Token, message: &str, left: &Value, right: &Value) -> Exit {
+        RuntimeError::new(
+            operator.line,
+            &format!(
+                "{}, got {} and {}.",
+                message,
+                self.describe(left),
+                self.describe(right)
+            ),
+        )
+        .into()
+    }
+
+    fn list_index(&self, bracket: &Token, index: &Value, len: usize) -> Result<usize, Exit> {
+        let Value::Number(index) = index else {
+            return Err(RuntimeError::new(
+                bracket.line,
+                &format!("List index must be a number, got {}.", self.describe(index)),
+            )
+            .into());
+        };
+
+        if *index < 0.0 || index.fract() != 0.0 {
+            return Err(RuntimeError::new(
+                bracket.line,
+                &format!(
+                    "List index must be a non-negative integer, got {}.",
+                    format_number(*index)
+                ),
+            )
+            .into());
+        }
+
+        let index = *index as usize;
+        if index >= len {
+            return Err(RuntimeError::new(
+                bracket.line,
+                &format!("List index {} out of bounds for length {}.", index, len),
+            )
+            .into());
+        }
+
+        Ok(index)
+    }
+
+    fn map_key(&self, bracket: &Token, key: Value) -> Result<String, Exit> {
+        match key {
+            Value::String(key) => Ok(key),
+            _ => Err(RuntimeError::new(
+                bracket.line,
+                &format!("Map key must be a string, got {}.", self.describe(&key)),
+            )
+            .into()),
+        }
+    }
+
+    fn look_up_variable(&self, name: &Token, resolution: Resolution) -> Result<Value, Exit> {
+        match resolution {
+            Resolution::Local(distance) => self.environment.borrow().get_at(distance, name),
+            Resolution::Global => self.globals.borrow().get(name),
+            Resolution::Unresolved => self.environment.borrow().get(name),
+        }
+    }
+
+    fn assign_variable(
+        &mut self,
+        name: &Token,
+        resolution: Resolution,
+        value: Value,
+    ) -> Result<(), Exit> {
+        match resolution {
+            Resolution::Local(distance) => self
+                .environment
+                .borrow_mut()
+                .assign_at(distance, name, value),
+            Resolution::Global => self.globals.borrow_mut().assign(name, value),
+            Resolution::Unresolved => self.environment.borrow_mut().assign(name, value),
+        }
+    }
+
+    pub(crate) fn evaluate(&mut self, expr: &expr::Expr) -> Result<Value, Exit> {
         expr.accept(self)
     }
 
-    fn is_truthy(&self, literal: &LiteralKind) -> bool {
+    pub(crate) fn is_truthy(&self, literal: &Value) -> bool {
         match literal {
-            LiteralKind::Bool(boolean) => *boolean,
-            LiteralKind::Nil => false,
+            Value::Bool(boolean) => *boolean,
+            Value::Nil => false,
             _ => true,
         }
     }
 
-    fn is_equal(&self, a: LiteralKind, b: LiteralKind) -> bool {
-        if a == LiteralKind::Nil && b == LiteralKind::Nil {
+    /// Primitives compare by value; lists, maps, functions, classes and
+    /// instances compare by identity, so two separately built empty lists are
+    /// not equal.
+    fn is_equal(&self, a: Value, b: Value) -> bool {
+        if a == Value::Nil && b == Value::Nil {
             return true;
         }
-        if a == LiteralKind::Nil {
+        if a == Value::Nil {
             return false;
         }
 
         match (a, b) {
-            (LiteralKind::Number(a), LiteralKind::Number(b)) => a == b,
-            (LiteralKind::String(a), LiteralKind::String(b)) => a == b,
-            (LiteralKind::Bool(a), LiteralKind::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(&a, &b),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(&a, &b),
+            (Value::Function(a), Value::Function(b)) => a == b,
+            (Value::NativeFunction(a), Value::NativeFunction(b)) => a == b,
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(&a, &b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(&a, &b),
             _ => false,
         }
     }
 
+    pub fn call(
+        &mut self,
+        callee: Value,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Exit> {
+        let (name, arity) = match &callee {
+            Value::Function(function) => (function.name(), function.arity()),
+            Value::NativeFunction(native) => (native.name, native.arity),
+            Value::Class(class) => (class.name.as_str(), class.arity()),
+            _ => {
+                return Err(
+                    RuntimeError::new(paren.line, "Can only call functions and classes.").into(),
+                );
+            }
+        };
+
+        if !arity.accepts(arguments.len()) {
+            return Err(RuntimeError::new(
+                paren.line,
+                &format!(
+                    "Expected {} arguments but got {} when calling '{}'.",
+                    arity,
+                    arguments.len(),
+                    name
+                ),
+            )
+            .into());
+        }
+
+        let function = match &callee {
+            Value::Function(function) => function.name(),
+            Value::Class(class) => &class.name,
+            Value::NativeFunction(native) => return native.call(self, paren, arguments),
+            _ => unreachable!(),
+        };
+        if self.call_stack.len() >= self.max_call_depth {
+            return Err(RuntimeError::new(paren.line, "Stack overflow.").into());
+        }
+        let function = function.to_string();
+        self.call_stack.push(Frame {
+            function: function.clone(),
+            line: paren.line,
+        });
+
+        let started = self.profile.is_some().then(Instant::now);
+        let result = match callee {
+            Value::Function(function) => function.call(self, arguments),
+            Value::Class(class) => Class::call(&class, self, arguments),
+            _ => unreachable!(),
+        };
+        if let (Some(profile), Some(started)) = (&mut self.profile, started) {
+            let entry = profile.entry(function).or_default();
+            entry.time += started.elapsed();
+            entry.calls += 1;
+        }
+
+        let result = match result {
+            Err(Exit::RuntimeError(mut error)) if error.trace.is_empty() => {
+                error.trace = self.call_stack.iter().rev().cloned().collect();
+                Err(error.into())
+            }
+            result => result,
+        };
+        self.call_stack.pop();
+        result
+    }
+
     pub fn execute_block(
         &mut self,
         statements: &[Stmt],
@@ -108,160 +893,444 @@ impl Interpreter {
     ) -> Result<(), Exit> {
         let previous = Rc::clone(&self.environment);
         self.environment = Rc::new(RefCell::new(environment));
-        let result = statements.iter().try_for_each(|stat| self.execute(stat));
+        let result = self.execute_nested(statements);
         self.environment = previous;
         result
     }
+
+    // Nested blocks and if/else chains are walked with an explicit stack
+    // instead of recursing through `execute`, so deeply nested programs don't
+    // overflow. Each frame remembers the environment to restore when it ends.
+    fn execute_nested(&mut self, statements: &[Stmt]) -> Result<(), Exit> {
+        let previous = Rc::clone(&self.environment);
+        let result = self.execute_frames(statements);
+        self.environment = previous;
+        result
+    }
+
+    fn execute_frames(&mut self, statements: &[Stmt]) -> Result<(), Exit> {
+        let mut frames = vec![(statements.iter(), None)];
+        'frames: while let Some((statements, _)) = frames.last_mut() {
+            let Some(mut statement) = statements.next() else {
+                if let Some((_, Some(enclosing))) = frames.pop() {
+                    self.environment = enclosing;
+                }
+                continue;
+            };
+
+            while let Stmt::If(stmt) = statement {
+                self.record_statement(statement);
+                let literal = self.evaluate(&stmt.condition)?;
+                statement = match (self.is_truthy(&literal), &stmt.else_branch) {
+                    (true, _) => &stmt.then_branch,
+                    (false, Some(else_branch)) => else_branch,
+                    (false, None) => continue 'frames,
+                };
+            }
+
+            match statement {
+                Stmt::Block(block) => {
+                    self.record_statement(statement);
+                    let enclosing = Rc::clone(&self.environment);
+                    self.environment = Rc::new(RefCell::new(Environment::new_with_enclosing(
+                        Rc::clone(&enclosing),
+                    )));
+                    frames.push((block.statements.iter(), Some(enclosing)));
+                }
+                _ => self.execute(statement)?,
+            }
+        }
+
+        Ok(())
+    }
 }
 
-impl ExpressionVisitor<Result<LiteralKind, Exit>> for Interpreter {
-    fn visit_assignment(&mut self, expr: &expr::Assignment) -> Result<LiteralKind, Exit> {
+impl ExpressionVisitor<Result<Value, Exit>> for Interpreter {
+    fn visit_assignment(&mut self, expr: &expr::Assignment) -> Result<Value, Exit> {
         let value = self.evaluate(&expr.value)?;
-        self.environment
-            .borrow_mut()
-            .assign(&expr.name, value.clone())?;
+        self.assign_variable(&expr.name, expr.resolution.get(), value.clone())?;
         Ok(value)
     }
 
-    fn visit_binary(&mut self, expr: &expr::Binary) -> Result<LiteralKind, Exit> {
-        let right = self.evaluate(&expr.right)?;
+    /// Evaluates the left operand before the right one.
+    fn visit_binary(&mut self, expr: &expr::Binary) -> Result<Value, Exit> {
         let left = self.evaluate(&expr.left)?;
+        let right = self.evaluate(&expr.right)?;
+        if let Some(result) = self.overloaded_operator(&expr.operator, &left, &right)? {
+            return Ok(result);
+        }
+
         match expr.operator.kind {
-            TokenKind::Minus => {
-                if let (LiteralKind::Number(left), LiteralKind::Number(right)) = (left, right) {
-                    Ok(LiteralKind::Number(left - right))
-                } else {
-                    report(expr.operator.line, "Operands must be numbers.");
-                    Err(Exit::RuntimeError)
-                }
-            }
-            TokenKind::Slash => {
-                if let (LiteralKind::Number(left), LiteralKind::Number(right)) = (left, right) {
-                    Ok(LiteralKind::Number(left / right))
-                } else {
-                    report(expr.operator.line, "Operands must be numbers.");
-                    Err(Exit::RuntimeError)
-                }
-            }
-            TokenKind::Star => {
-                if let (LiteralKind::Number(left), LiteralKind::Number(right)) = (left, right) {
-                    Ok(LiteralKind::Number(left * right))
-                } else {
-                    report(expr.operator.line, "Operands must be numbers.");
-                    Err(Exit::RuntimeError)
-                }
-            }
-            TokenKind::Plus => match (left, right) {
-                (LiteralKind::Number(left), LiteralKind::Number(right)) => {
-                    Ok(LiteralKind::Number(left + right))
-                }
-                (LiteralKind::String(left), LiteralKind::String(right)) => {
-                    Ok(LiteralKind::String(format!("{left}{right}")))
-                }
-                _ => {
-                    report(
-                        expr.operator.line,
-                        "Operands must be two numbers or two strings.",
-                    );
-                    Err(Exit::RuntimeError)
+            TokenKind::Minus => match (&left, &right) {
+                (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left - right)),
+                _ => Err(self.operands_error(
+                    &expr.operator,
+                    "Operands must be numbers",
+                    &left,
+                    &right,
+                )),
+            },
+            TokenKind::Slash => match (&left, &right) {
+                (Value::Number(_), Value::Number(right))
+                    if *right == 0.0 && !self.ieee_division =>
+                {
+                    Err(RuntimeError::new(expr.operator.line, "Division by zero.").into())
                 }
+                (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left / right)),
+                _ => Err(self.operands_error(
+                    &expr.operator,
+                    "Operands must be numbers",
+                    &left,
+                    &right,
+                )),
             },
-            TokenKind::Greater => {
-                if let (LiteralKind::Number(left), LiteralKind::Number(right)) = (left, right) {
-                    Ok(LiteralKind::Bool(left > right))
-                } else {
-                    report(expr.operator.line, "Operands must be numbers.");
-                    Err(Exit::RuntimeError)
+            TokenKind::Star => match (&left, &right) {
+                (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left * right)),
+                _ => Err(self.operands_error(
+                    &expr.operator,
+                    "Operands must be numbers",
+                    &left,
+                    &right,
+                )),
+            },
+            TokenKind::Plus => match (&left, &right) {
+                (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left + right)),
+                (Value::String(left), Value::String(right)) => {
+                    Ok(Value::String(format!("{left}{right}")))
                 }
-            }
-            TokenKind::GreaterEqual => {
-                if let (LiteralKind::Number(left), LiteralKind::Number(right)) = (left, right) {
-                    Ok(LiteralKind::Bool(left >= right))
-                } else {
-                    report(expr.operator.line, "Operands must be numbers.");
-                    Err(Exit::RuntimeError)
+                (Value::String(left), Value::Number(right)) => {
+                    Ok(Value::String(format!("{left}{}", format_number(*right))))
                 }
-            }
-            TokenKind::Less => {
-                if let (LiteralKind::Number(left), LiteralKind::Number(right)) = (left, right) {
-                    Ok(LiteralKind::Bool(left < right))
-                } else {
-                    report(expr.operator.line, "Operands must be numbers.");
-                    Err(Exit::RuntimeError)
+                (Value::Number(left), Value::String(right)) => {
+                    Ok(Value::String(format!("{}{right}", format_number(*left))))
                 }
-            }
-            TokenKind::LessEqual => {
-                if let (LiteralKind::Number(left), LiteralKind::Number(right)) = (left, right) {
-                    Ok(LiteralKind::Bool(left <= right))
-                } else {
-                    report(expr.operator.line, "Operands must be numbers.");
-                    Err(Exit::RuntimeError)
+                _ => Err(self.operands_error(
+                    &expr.operator,
+                    "Operands must be two numbers or two strings",
+                    &left,
+                    &right,
+                )),
+            },
+            TokenKind::Greater => match (&left, &right) {
+                (Value::Number(left), Value::Number(right)) => Ok(Value::Bool(left > right)),
+                (Value::String(left), Value::String(right)) => Ok(Value::Bool(left > right)),
+                _ => Err(self.operands_error(
+                    &expr.operator,
+                    "Operands must be numbers",
+                    &left,
+                    &right,
+                )),
+            },
+            TokenKind::GreaterEqual => match (&left, &right) {
+                (Value::Number(left), Value::Number(right)) => Ok(Value::Bool(left >= right)),
+                (Value::String(left), Value::String(right)) => Ok(Value::Bool(left >= right)),
+                _ => Err(self.operands_error(
+                    &expr.operator,
+                    "Operands must be numbers",
+                    &left,
+                    &right,
+                )),
+            },
+            TokenKind::Less => match (&left, &right) {
+                (Value::Number(left), Value::Number(right)) => Ok(Value::Bool(left < right)),
+                (Value::String(left), Value::String(right)) => Ok(Value::Bool(left < right)),
+                _ => Err(self.operands_error(
+                    &expr.operator,
+                    "Operands must be numbers",
+                    &left,
+                    &right,
+                )),
+            },
+            TokenKind::LessEqual => match (&left, &right) {
+                (Value::Number(left), Value::Number(right)) => Ok(Value::Bool(left <= right)),
+                (Value::String(left), Value::String(right)) => Ok(Value::Bool(left <= right)),
+                _ => Err(self.operands_error(
+                    &expr.operator,
+                    "Operands must be numbers",
+                    &left,
+                    &right,
+                )),
+            },
+            TokenKind::Is => match (&left, &right) {
+                (Value::Instance(instance), Value::Class(class)) => {
+                    Ok(Value::Bool(instance.borrow().class.is_subclass_of(class)))
                 }
-            }
-            TokenKind::BangEqual => Ok(LiteralKind::Bool(!self.is_equal(left, right))),
-            TokenKind::EqualEqual => Ok(LiteralKind::Bool(self.is_equal(left, right))),
+                (_, Value::Class(_)) => Ok(Value::Bool(false)),
+                _ => Err(RuntimeError::new(
+                    expr.operator.line,
+                    &format!(
+                        "Right operand of 'is' must be a class, got {}.",
+                        self.describe(&right)
+                    ),
+                )
+                .into()),
+            },
+            TokenKind::BangEqual => Ok(Value::Bool(!self.is_equal(left, right))),
+            TokenKind::EqualEqual => Ok(Value::Bool(self.is_equal(left, right))),
             _ => unreachable!(),
         }
     }
 
-    fn visit_grouping(&mut self, expr: &expr::Grouping) -> Result<LiteralKind, Exit> {
+    fn visit_grouping(&mut self, expr: &expr::Grouping) -> Result<Value, Exit> {
         self.evaluate(&expr.expr)
     }
 
-    fn visit_literal(&self, expr: &expr::Literal) -> Result<LiteralKind, Exit> {
-        Ok(expr.value.clone())
+    fn visit_literal(&self, expr: &expr::Literal) -> Result<Value, Exit> {
+        Ok(expr.value.clone().into())
     }
 
-    fn visit_logical(&mut self, expr: &expr::Logical) -> Result<LiteralKind, Exit> {
+    fn visit_logical(&mut self, expr: &expr::Logical) -> Result<Value, Exit> {
         let left = self.evaluate(&expr.left)?;
-        if expr.operator.kind == TokenKind::Or {
-            if self.is_truthy(&left) {
-                return Ok(left);
-            };
-        } else if !self.is_truthy(&left) {
-            return Ok(left);
+        match expr.operator.kind {
+            TokenKind::Or if self.is_truthy(&left) => Ok(left),
+            TokenKind::And if !self.is_truthy(&left) => Ok(left),
+            // xor needs both sides, so it never short-circuits.
+            TokenKind::Xor => {
+                let right = self.evaluate(&expr.right)?;
+                Ok(Value::Bool(self.is_truthy(&left) != self.is_truthy(&right)))
+            }
+            _ => self.evaluate(&expr.right),
         }
-
-        self.evaluate(&expr.right)
     }
 
-    fn visit_unary(&mut self, expr: &expr::Unary) -> Result<LiteralKind, Exit> {
+    fn visit_unary(&mut self, expr: &expr::Unary) -> Result<Value, Exit> {
         let right = self.evaluate(&expr.right)?;
         match expr.operator.kind {
             TokenKind::Minus => match right {
-                LiteralKind::Number(number) => Ok(LiteralKind::Number(-number)),
-                _ => {
-                    report(expr.operator.line, "Operand must be a number.");
-                    Err(Exit::RuntimeError)
-                }
+                Value::Number(number) => Ok(Value::Number(-number)),
+                _ => Err(RuntimeError::new(
+                    expr.operator.line,
+                    &format!("Operand must be a number, got {}.", self.describe(&right)),
+                )
+                .into()),
             },
-            TokenKind::Bang => Ok(LiteralKind::Bool(!self.is_truthy(&right))),
+            TokenKind::Bang => Ok(Value::Bool(!self.is_truthy(&right))),
             _ => unreachable!(),
         }
     }
 
-    fn visit_variable(&mut self, expr: &expr::Variable) -> Result<LiteralKind, Exit> {
-        self.environment.borrow().get(&expr.name)
+    fn visit_postfix(&mut self, expr: &expr::Postfix) -> Result<Value, Exit> {
+        let Expr::Variable(variable) = expr.target.as_ref() else {
+            return Err(RuntimeError::new(expr.operator.line, "Invalid increment target.").into());
+        };
+
+        let value = self.look_up_variable(&variable.name, variable.resolution.get())?;
+        let Value::Number(number) = value else {
+            return Err(RuntimeError::new(
+                expr.operator.line,
+                &format!("Operand must be a number, got {}.", self.describe(&value)),
+            )
+            .into());
+        };
+
+        let updated = match expr.operator.kind {
+            TokenKind::PlusPlus => number + 1.0,
+            TokenKind::MinusMinus => number - 1.0,
+            _ => unreachable!(),
+        };
+        self.assign_variable(
+            &variable.name,
+            variable.resolution.get(),
+            Value::Number(updated),
+        )?;
+        Ok(value)
+    }
+
+    fn visit_variable(&mut self, expr: &expr::Variable) -> Result<Value, Exit> {
+        self.look_up_variable(&expr.name, expr.resolution.get())
+    }
+
+    fn visit_call(&mut self, expr: &expr::Call) -> Result<Value, Exit> {
+        let callee = self.evaluate(&expr.callee)?;
+        let mut arguments = Vec::new();
+        for argument in expr.arguments.iter() {
+            arguments.push(self.evaluate(argument)?);
+        }
+
+        self.call(callee, &expr.paren, arguments)
+    }
+
+    fn visit_lambda(&mut self, expr: &expr::Lambda) -> Result<Value, Exit> {
+        Ok(Value::Function(Function::lambda(
+            expr,
+            Rc::clone(&self.environment),
+        )))
     }
 
-    fn visit_call(&mut self, expr: &expr::Call) -> Result<LiteralKind, Exit> {
-        todo!()
+    fn visit_list(&mut self, expr: &expr::List) -> Result<Value, Exit> {
+        let mut elements = Vec::new();
+        for element in expr.elements.iter() {
+            elements.push(self.evaluate(element)?);
+        }
+
+        Ok(Value::List(Rc::new(RefCell::new(elements))))
     }
 
-    fn visit_get(&mut self, expr: &expr::Get) -> Result<LiteralKind, Exit> {
-        todo!()
+    fn visit_map(&mut self, expr: &expr::Map) -> Result<Value, Exit> {
+        let mut map = HashMap::new();
+        for (key, value) in expr.entries.iter() {
+            let key = self.evaluate(key)?;
+            let key = self.map_key(&expr.brace, key)?;
+            let value = self.evaluate(value)?;
+            map.insert(key, value);
+        }
+
+        Ok(Value::Map(Rc::new(RefCell::new(map))))
     }
 
-    fn visit_set(&mut self, expr: &expr::Set) -> Result<LiteralKind, Exit> {
-        todo!()
+    fn visit_index(&mut self, expr: &expr::Index) -> Result<Value, Exit> {
+        let object = self.evaluate(&expr.object)?;
+        let index = self.evaluate(&expr.index)?;
+        match &object {
+            Value::List(list) => {
+                let list = list.borrow();
+                let index = self.list_index(&expr.bracket, &index, list.len())?;
+                Ok(list[index].clone())
+            }
+            Value::Map(map) => {
+                let key = self.map_key(&expr.bracket, index)?;
+                Ok(map.borrow().get(&key).cloned().unwrap_or(Value::Nil))
+            }
+            _ => Err(RuntimeError::new(
+                expr.bracket.line,
+                &format!(
+                    "Only lists and maps can be indexed, got {}.",
+                    self.describe(&object)
+                ),
+            )
+            .into()),
+        }
     }
 
-    fn visit_this(&mut self, expr: &expr::This) -> Result<LiteralKind, Exit> {
-        todo!()
+    fn visit_set_index(&mut self, expr: &expr::SetIndex) -> Result<Value, Exit> {
+        let object = self.evaluate(&expr.object)?;
+        let index = self.evaluate(&expr.index)?;
+        let value = self.evaluate(&expr.value)?;
+        match &object {
+            Value::List(list) => {
+                let mut list = list.borrow_mut();
+                let index = self.list_index(&expr.bracket, &index, list.len())?;
+                list[index] = value.clone();
+                Ok(value)
+            }
+            Value::Map(map) => {
+                let key = self.map_key(&expr.bracket, index)?;
+                map.borrow_mut().insert(key, value.clone());
+                Ok(value)
+            }
+            _ => Err(RuntimeError::new(
+                expr.bracket.line,
+                &format!(
+                    "Only lists and maps can be indexed, got {}.",
+                    self.describe(&object)
+                ),
+            )
+            .into()),
+        }
     }
 
-    fn visit_super(&mut self, expr: &expr::Super) -> Result<LiteralKind, Exit> {
-        todo!()
+    fn visit_get(&mut self, expr: &expr::Get) -> Result<Value, Exit> {
+        let property = match self.evaluate(&expr.object)? {
+            Value::Instance(instance) => Instance::get(&instance, &expr.name)?,
+            Value::Class(class) => class.get(&expr.name)?,
+            object => {
+                return Err(RuntimeError::new(
+                    expr.name.line,
+                    &format!(
+                        "Only instances have properties, got {}.",
+                        self.describe(&object)
+                    ),
+                )
+                .into())
+            }
+        };
+
+        // Getters run as soon as they are looked up.
+        match property {
+            Value::Function(getter) if getter.is_getter => {
+                self.call(Value::Function(getter), &expr.name, Vec::new())
+            }
+            property => Ok(property),
+        }
+    }
+
+    fn visit_set(&mut self, expr: &expr::Set) -> Result<Value, Exit> {
+        let Value::Instance(instance) = self.evaluate(&expr.object)? else {
+            return Err(RuntimeError::new(expr.name.line, "Only instances have fields.").into());
+        };
+
+        let value = self.evaluate(&expr.value)?;
+        instance.borrow_mut().set(&expr.name, value.clone());
+        Ok(value)
+    }
+
+    fn visit_this(&mut self, expr: &expr::This) -> Result<Value, Exit> {
+        self.look_up_variable(&expr.keyword, expr.resolution.get())
+    }
+
+    fn visit_super(&mut self, expr: &expr::Super) -> Result<Value, Exit> {
+        let resolution = expr.resolution.get();
+        let Value::Class(superclass) = self.look_up_variable(&expr.keyword, resolution)? else {
+            unreachable!("'super' is only ever bound to a class");
+        };
+        let this = Token::new(
+            TokenKind::This,
+            "this".to_string(),
+            LiteralKind::Nil,
+            expr.keyword.line,
+        );
+        // `this` is bound in the scope just inside the one holding `super`.
+        let object = match resolution {
+            Resolution::Local(distance) => {
+                self.look_up_variable(&this, Resolution::Local(distance - 1))?
+            }
+            resolution => self.look_up_variable(&this, resolution)?,
+        };
+
+        match superclass.find_method(expr.method.lexeme()) {
+            Some(method) => Ok(Value::Function(method.bind(object))),
+            None => Err(RuntimeError::new(
+                expr.method.line,
+                &format!("Undefined property '{}'.", expr.method.lexeme),
+            )
+            .into()),
+        }
+    }
+
+    fn visit_loop(&mut self, expr: &expr::Loop) -> Result<Value, Exit> {
+        loop {
+            let environment = Environment::new_with_enclosing(Rc::clone(&self.environment));
+            match self.execute_block(&expr.body, environment) {
+                Ok(()) | Err(Exit::Continue) => (),
+                Err(Exit::Break(value)) => return Ok(value),
+                Err(exit) => return Err(exit),
+            }
+        }
+    }
+
+    fn visit_block_expression(&mut self, expr: &expr::BlockExpression) -> Result<Value, Exit> {
+        let previous = Rc::clone(&self.environment);
+        self.environment = Rc::new(RefCell::new(Environment::new_with_enclosing(Rc::clone(
+            &previous,
+        ))));
+        let result = self
+            .execute_nested(&expr.statements)
+            .and_then(|()| match &expr.value {
+                Some(value) => self.evaluate(value),
+                None => Ok(Value::Nil),
+            });
+        self.environment = previous;
+        result
+    }
+
+    fn visit_interpolation(&mut self, expr: &expr::Interpolation) -> Result<Value, Exit> {
+        let mut string = String::new();
+        for part in expr.parts.iter() {
+            let value = self.evaluate(part)?;
+            string.push_str(&self.stringify(value, expr.line)?);
+        }
+
+        Ok(Value::String(string))
     }
 }
 
@@ -272,24 +1341,30 @@ impl StatementVisitor<Result<(), Exit>> for Interpreter {
     }
 
     fn visit_print(&mut self, stmt: &stmt::Print) -> Result<(), Exit> {
-        let value = self.evaluate(&stmt.expression)?;
-        println!("{}", self.stringify(value));
+        let mut values = Vec::new();
+        for expression in stmt.expressions.iter() {
+            let value = self.evaluate(expression)?;
+            values.push(self.stringify(value, expression.line())?);
+        }
+        let line = stmt.expressions.first().map_or(0, Expr::line);
+        self.write_output(line, &format!("{}\n", values.join(" ")))?;
         Ok(())
     }
 
     fn visit_var(&mut self, stmt: &stmt::Var) -> Result<(), Exit> {
-        let value = if let Expr::Literal(Literal {
-            value: LiteralKind::Nil,
-        }) = *stmt.initializer
-        {
-            self.evaluate(&stmt.initializer)?
-        } else {
-            self.evaluate(&stmt.initializer)?
+        let Some(initializer) = &stmt.initializer else {
+            self.environment
+                .borrow_mut()
+                .declare(stmt.name.lexeme.to_string());
+            return Ok(());
         };
 
-        self.environment
-            .borrow_mut()
-            .define(stmt.name.lexeme.clone(), value);
+        let value = self.evaluate(initializer)?;
+        let mut environment = self.environment.borrow_mut();
+        match stmt.constant {
+            true => environment.define_constant(stmt.name.lexeme.to_string(), value),
+            false => environment.define(stmt.name.lexeme.to_string(), value),
+        }
         Ok(())
     }
 
@@ -304,9 +1379,9 @@ impl StatementVisitor<Result<(), Exit>> for Interpreter {
     fn visit_if(&mut self, stmt: &stmt::If) -> Result<(), Exit> {
         let literal = self.evaluate(&stmt.condition)?;
         if self.is_truthy(&literal) {
-            self.execute(&stmt.then_branch)?;
+            self.execute_nested(std::slice::from_ref(&stmt.then_branch))?;
         } else if let Some(else_branch) = &stmt.else_branch {
-            self.execute(&else_branch)?;
+            self.execute_nested(std::slice::from_ref(else_branch))?;
         }
 
         Ok(())
@@ -318,21 +1393,164 @@ impl StatementVisitor<Result<(), Exit>> for Interpreter {
             if !self.is_truthy(&literal) {
                 break;
             }
-            self.execute(&stmt.body)?;
+
+            match self.execute(&stmt.body) {
+                Ok(()) | Err(Exit::Continue) => (),
+                Err(Exit::Break(_)) => break,
+                Err(exit) => return Err(exit),
+            }
+
+            if let Some(increment) = &stmt.increment {
+                self.evaluate(increment)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_for_in(&mut self, stmt: &stmt::ForIn) -> Result<(), Exit> {
+        let iterable = self.evaluate(&stmt.iterable)?;
+        let items = match &iterable {
+            Value::List(list) => list.borrow().clone(),
+            Value::Map(map) => {
+                let mut keys: Vec<String> = map.borrow().keys().cloned().collect();
+                keys.sort();
+                keys.into_iter().map(Value::String).collect()
+            }
+            _ => {
+                return Err(RuntimeError::new(
+                    stmt.iterable.line(),
+                    &format!(
+                        "Can only iterate over lists and maps, got {}.",
+                        self.describe(&iterable)
+                    ),
+                )
+                .into())
+            }
+        };
+
+        for item in items {
+            let mut environment = Environment::new_with_enclosing(Rc::clone(&self.environment));
+            environment.define(stmt.variable.lexeme.to_string(), item);
+            match self.execute_block(std::slice::from_ref(&stmt.body), environment) {
+                Ok(()) | Err(Exit::Continue) => (),
+                Err(Exit::Break(_)) => break,
+                Err(exit) => return Err(exit),
+            }
         }
 
         Ok(())
     }
 
     fn visit_function(&mut self, stmt: &stmt::Function) -> Result<(), Exit> {
-        todo!()
+        let function = Function::new(stmt, Rc::clone(&self.environment));
+        self.environment
+            .borrow_mut()
+            .define(stmt.name.lexeme.to_string(), Value::Function(function));
+        Ok(())
     }
 
     fn visit_return(&mut self, stmt: &stmt::Return) -> Result<(), Exit> {
-        todo!()
+        let value = match &stmt.value {
+            Some(value) => self.evaluate(value)?,
+            None => Value::Nil,
+        };
+        Err(Exit::Return(value))
     }
 
     fn visit_class(&mut self, stmt: &stmt::Class) -> Result<(), Exit> {
-        todo!()
+        let superclass = match &stmt.super_class {
+            Some(expr) => match self.evaluate(expr)? {
+                Value::Class(superclass) => Some(superclass),
+                _ => {
+                    return Err(
+                        RuntimeError::new(stmt.name.line, "Superclass must be a class.").into(),
+                    )
+                }
+            },
+            None => None,
+        };
+
+        self.environment
+            .borrow_mut()
+            .declare(stmt.name.lexeme.to_string());
+
+        // Methods of a subclass close over a scope that binds `super`.
+        let closure = match &superclass {
+            Some(superclass) => {
+                let mut environment = Environment::new_with_enclosing(Rc::clone(&self.environment));
+                environment.define("super".to_string(), Value::Class(Rc::clone(superclass)));
+                Rc::new(RefCell::new(environment))
+            }
+            None => Rc::clone(&self.environment),
+        };
+
+        let mut methods = HashMap::new();
+        for method in stmt.methods.iter() {
+            let Stmt::Function(method) = method else {
+                continue;
+            };
+            let is_initializer = method.name.lexeme() == "init";
+            methods.insert(
+                method.name.lexeme.to_string(),
+                Function::method(method, Rc::clone(&closure), is_initializer),
+            );
+        }
+
+        let mut class_methods = HashMap::new();
+        for method in stmt.class_methods.iter() {
+            let Stmt::Function(method) = method else {
+                continue;
+            };
+            class_methods.insert(
+                method.name.lexeme.to_string(),
+                Function::new(method, Rc::clone(&closure)),
+            );
+        }
+
+        let class = Class {
+            name: stmt.name.lexeme.to_string(),
+            superclass,
+            methods,
+            class_methods,
+        };
+        self.environment
+            .borrow_mut()
+            .define(stmt.name.lexeme.to_string(), Value::Class(Rc::new(class)));
+        Ok(())
+    }
+
+    fn visit_switch(&mut self, stmt: &stmt::Switch) -> Result<(), Exit> {
+        let value = self.evaluate(&stmt.scrutinee)?;
+        let mut matched = stmt.default.as_ref();
+        'arms: for (patterns, body) in stmt.arms.iter() {
+            for pattern in patterns.iter() {
+                let pattern = self.evaluate(pattern)?;
+                if self.is_equal(value.clone(), pattern) {
+                    matched = Some(body);
+                    break 'arms;
+                }
+            }
+        }
+
+        match matched {
+            Some(body) => self.execute_block(
+                body,
+                Environment::new_with_enclosing(Rc::clone(&self.environment)),
+            ),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_break(&mut self, stmt: &stmt::Break) -> Result<(), Exit> {
+        let value = match &stmt.value {
+            Some(value) => self.evaluate(value)?,
+            None => Value::Nil,
+        };
+        Err(Exit::Break(value))
+    }
+
+    fn visit_continue(&mut self, _stmt: &stmt::Continue) -> Result<(), Exit> {
+        Err(Exit::Continue)
     }
 }
@@ -1,17 +1,151 @@
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+};
+
+use diagnostic::{Diagnostic, Diagnostics, Severity};
+use interpreter::RuntimeError;
 use token::{Token, TokenKind};
 
 pub mod ast_printer;
+pub mod callable;
+pub mod diagnostic;
 pub mod environement;
 pub mod expr;
+pub mod formatter;
+pub mod highlight;
 pub mod interpreter;
+pub mod natives;
+pub mod optimizer;
 pub mod parser;
+pub mod program;
+pub mod resolver;
 pub mod scanner;
 pub mod stmt;
 pub mod token;
+pub mod transform;
+
+thread_local! {
+    static ERROR_WRITER: RefCell<Box<dyn Write>> = RefCell::new(Box::new(io::stderr()));
+    static DIAGNOSTICS: RefCell<Diagnostics> = RefCell::new(Diagnostics::new());
+}
+
+/// Sends every diagnostic reported on this thread to `writer` instead of
+/// stderr, handing back the writer it replaces. Put that one back, or call
+/// `reset_error_writer`, once done capturing.
+///
+/// ```
+/// use std::{cell::RefCell, io::Write, rc::Rc};
+///
+/// use codecrafters_interpreter::{reset_error_writer, scanner::Scanner, set_error_writer};
+///
+/// #[derive(Clone, Default)]
+/// struct Buffer(Rc<RefCell<Vec<u8>>>);
+///
+/// impl Write for Buffer {
+///     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+///         self.0.borrow_mut().write(buf)
+///     }
+///
+///     fn flush(&mut self) -> std::io::Result<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// let buffer = Buffer::default();
+/// set_error_writer(buffer.clone());
+/// let (_, errors) = Scanner::new("var a = @;".to_string()).into_parts();
+/// reset_error_writer();
+///
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(
+///     String::from_utf8(buffer.0.take()).unwrap(),
+///     "[line 1] Error: Unexpected character: @\n"
+/// );
+/// ```
+pub fn set_error_writer(writer: impl Write + 'static) -> Box<dyn Write> {
+    ERROR_WRITER.with(|sink| sink.replace(Box::new(writer)))
+}
+
+/// Points diagnostics back at stderr.
+pub fn reset_error_writer() {
+    set_error_writer(io::stderr());
+}
+
+/// Writes one line to the current error writer.
+pub(crate) fn emit(line: &str) {
+    ERROR_WRITER.with(|sink| {
+        // There is nowhere left to report a failing error writer to.
+        let _ = writeln!(sink.borrow_mut(), "{}", line);
+    });
+}
+
+/// Records `diagnostic` on this thread and prints it to the error writer,
+/// returning the severity it ended up with.
+pub fn diagnose(diagnostic: Diagnostic) -> Severity {
+    DIAGNOSTICS.with(|diagnostics| {
+        let mut diagnostics = diagnostics.borrow_mut();
+        let diagnostic = diagnostics.push(diagnostic);
+        emit(&diagnostic.to_string());
+        diagnostic.severity
+    })
+}
+
+/// Makes every warning reported on this thread from now on an error.
+pub fn deny_warnings(deny: bool) {
+    DIAGNOSTICS.with(|diagnostics| {
+        let previous = diagnostics.take();
+        *diagnostics.borrow_mut() = previous.with_deny_warnings(deny);
+    });
+}
+
+/// Whether an error-severity diagnostic has been reported on this thread.
+pub fn has_errors() -> bool {
+    DIAGNOSTICS.with(|diagnostics| diagnostics.borrow().has_errors())
+}
+
+/// Hands back the diagnostics reported on this thread so far, clearing them.
+///
+/// ```
+/// use codecrafters_interpreter::{
+///     diagnostic::Diagnostic, has_errors, scanner::Scanner, take_diagnostics,
+/// };
+///
+/// Scanner::new("var a = 1;\n  var b = @;".to_string()).into_parts();
+/// assert!(has_errors());
+/// assert_eq!(
+///     take_diagnostics(),
+///     vec![Diagnostic::error(2, "Unexpected character: @").with_column(11)]
+/// );
+/// assert!(!has_errors());
+/// ```
+pub fn take_diagnostics() -> Vec<Diagnostic> {
+    DIAGNOSTICS.with(|diagnostics| diagnostics.borrow_mut().take())
+}
 
 pub fn report(line: usize, message: &str) {
-    let err = format!("[line {}] Error: {}", line, message);
-    eprintln!("{}", err);
+    diagnose(Diagnostic::error(line, message));
+}
+
+/// Reports a runtime error followed by the calls it was raised under. Runs
+/// of the same frame, as left by deep recursion, are folded into one line.
+pub fn report_runtime(error: &RuntimeError) {
+    report(error.line, &error.message);
+    let mut frames = error.trace.iter().peekable();
+    while let Some(frame) = frames.next() {
+        emit(&format!("    {}", frame));
+        let mut repeated = 0;
+        while frames.next_if_eq(&frame).is_some() {
+            repeated += 1;
+        }
+        if repeated > 0 {
+            emit(&format!("    ... {} more of the same", repeated));
+        }
+    }
+}
+
+pub fn warn(line: usize, message: &str) -> Severity {
+    diagnose(Diagnostic::warning(line, message))
 }
 
 pub fn error(token: Token, message: &str) {
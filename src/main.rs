@@ -2,16 +2,42 @@ use std::env;
 use std::fs;
 use std::io::{self, Write};
 use std::process;
+use std::thread;
 
 use codecrafters_interpreter::ast_printer::AstPrinter;
+use codecrafters_interpreter::formatter::format_source;
 use codecrafters_interpreter::interpreter::Interpreter;
 use codecrafters_interpreter::parser::Parser;
-use codecrafters_interpreter::scanner::Scanner;
+use codecrafters_interpreter::resolver::Resolver;
+use codecrafters_interpreter::scanner::{is_incomplete, Scanner};
+use codecrafters_interpreter::{deny_warnings, report_runtime};
+
+// Every pass walks the tree recursively and each Lox call nests a few Rust
+// calls, so a program at the interpreter's call depth limit can need far more
+// than the main thread's stack, particularly in a debug build.
+const STACK_SIZE: usize = 512 * 1024 * 1024;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let interpreter = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("failed to start the interpreter thread");
+    if interpreter.join().is_err() {
+        process::exit(101);
+    }
+}
+
+fn run() {
+    let (flags, args): (Vec<String>, Vec<String>) =
+        env::args().partition(|arg| arg.starts_with("--"));
+    deny_warnings(flags.iter().any(|flag| flag == "--deny-warnings"));
+    if args.get(1).is_some_and(|command| command == "repl") {
+        repl();
+        return;
+    }
+
     if args.len() < 3 {
-        writeln!(io::stderr(), "Usage: {} tokenize <filename>", args[0]).unwrap();
+        eprintln!("Usage: {} tokenize <filename>", args[0]);
         return;
     }
 
@@ -19,57 +45,139 @@ fn main() {
     let filename = &args[2];
 
     let file_contents = fs::read_to_string(filename).unwrap_or_else(|_| {
-        writeln!(io::stderr(), "Failed to read file {}", filename).unwrap();
+        eprintln!("Failed to read file {}", filename);
         String::new()
     });
 
-    if !file_contents.is_empty() {
-        let mut scanner = Scanner::new(file_contents);
-        let tokens = scanner.scan_tokens();
-        let mut ast_printer = AstPrinter {};
-        let mut interpreter = Interpreter::new();
-        let mut parser = Parser::new(tokens.clone());
-
-        match command.as_str() {
-            "tokenize" => {
-                for token in tokens {
-                    println!("{}", token)
-                }
+    if command == "fmt" {
+        match format_source(&file_contents) {
+            Ok(formatted) => print!("{}", formatted),
+            Err(_) => process::exit(65),
+        }
+        return;
+    }
 
-                if scanner.errors() {
-                    process::exit(65);
-                }
+    let (tokens, scan_errors) = Scanner::new(file_contents).into_parts();
+    let mut ast_printer = AstPrinter {};
+    let mut interpreter = Interpreter::new();
+    if flags.iter().any(|flag| flag == "--trace") {
+        interpreter = interpreter.with_trace(io::stderr());
+    }
+    if flags.iter().any(|flag| flag == "--profile") {
+        interpreter = interpreter.with_profiling(true);
+    }
+    let coverage = flags.iter().any(|flag| flag == "--coverage");
+    interpreter = interpreter.with_coverage(coverage);
+
+    match command.as_str() {
+        "tokenize" => {
+            for token in tokens {
+                println!("{}", token)
+            }
+
+            if !scan_errors.is_empty() {
+                process::exit(65);
+            }
+        }
+        "tokens-json" => {
+            let tokens: Vec<String> = tokens
+                .iter()
+                .map(|token| format!("  {}", token.to_json()))
+                .collect();
+            println!("[\n{}\n]", tokens.join(",\n"));
+
+            if !scan_errors.is_empty() {
+                process::exit(65);
             }
-            "parse" => match parser.parse_expression() {
-                Ok(expr) => println!("{}", ast_printer.print(expr)),
+        }
+        "parse" => match Parser::new(tokens).parse_expression() {
+            Ok(expr) => println!("{}", ast_printer.print(expr)),
+            Err(_) => process::exit(65),
+        },
+        "ast" => match Parser::new(tokens).parse() {
+            Ok(statements) => println!("{}", ast_printer.print_statements(&statements)),
+            Err(_) => process::exit(65),
+        },
+        "evaluate" => {
+            let expression = match Parser::new(tokens).parse_expression() {
+                Ok(expr) => expr,
                 Err(_) => process::exit(65),
-            },
-            "evaluate" => {
-                let expression = match parser.parse_expression() {
-                    Ok(expr) => expr,
-                    Err(_) => process::exit(65),
-                };
-                match interpreter.interpret_expression(&expression) {
-                    Ok(result) => println!("{}", result),
-                    Err(_) => process::exit(70),
+            };
+            match interpreter.interpret_expression(&expression) {
+                Ok(result) => println!("{}", result),
+                Err(error) => {
+                    report_runtime(&error);
+                    process::exit(70);
                 }
             }
-            "run" => {
-                let statements = match parser.parse() {
-                    Ok(stmt) => stmt,
-                    Err(_) => process::exit(65),
-                };
+        }
+        "run" => {
+            let statements = match Parser::new(tokens).parse() {
+                Ok(stmt) => stmt,
+                Err(_) => process::exit(65),
+            };
+            let mut resolver = Resolver::new().with_local_redeclaration_errors(true);
+            resolver.resolve(&statements);
+            if resolver.errors() {
+                process::exit(65);
+            }
 
-                if let Err(_) = interpreter.interpret(&statements) {
-                    process::exit(70);
-                };
+            let result = interpreter.interpret(&statements);
+            if let Some(report) = interpreter.profile_report() {
+                eprint!("{}", report);
+            }
+            if coverage {
+                let uncovered: Vec<String> = interpreter
+                    .uncovered_lines(&statements)
+                    .iter()
+                    .map(usize::to_string)
+                    .collect();
+                eprintln!("Uncovered lines: {}", uncovered.join(", "));
             }
-            _ => {
-                writeln!(io::stderr(), "Unknown command: {}", command).unwrap();
-                return;
+            if let Err(error) = result {
+                report_runtime(&error);
+                process::exit(70);
+            };
+        }
+        "check" => {
+            let parsed = Parser::new(tokens).parse();
+            let mut resolver = Resolver::new().with_local_redeclaration_errors(true);
+            if let Ok(statements) = &parsed {
+                resolver.resolve(statements);
+            }
+
+            if !scan_errors.is_empty() || parsed.is_err() || resolver.errors() {
+                process::exit(65);
             }
         }
-    } else {
-        println!("EOF  null"); // Placeholder, remove this line when implementing the scanner
+        _ => {
+            eprintln!("Unknown command: {}", command);
+        }
+    }
+}
+
+fn repl() {
+    let mut interpreter = Interpreter::new();
+    let mut source = String::new();
+    loop {
+        print!("{}", if source.is_empty() { "> " } else { "... " });
+        io::stdout().flush().unwrap();
+        if !matches!(io::stdin().read_line(&mut source), Ok(1..)) {
+            break;
+        }
+        if is_incomplete(&source) {
+            continue;
+        }
+
+        let (tokens, scan_errors) = Scanner::new(std::mem::take(&mut source)).into_parts();
+        if !scan_errors.is_empty() {
+            continue;
+        }
+        let Ok(statements) = Parser::new(tokens).with_repl(true).parse() else {
+            continue;
+        };
+        if let Err(error) = interpreter.interpret_repl(&statements) {
+            report_runtime(&error);
+        }
     }
 }
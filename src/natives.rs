@@ -0,0 +1,464 @@
+use crate::{
+    callable::{Arity, NativeFn, NativeFunction},
+    environement::Environment,
+    interpreter::{Exit, Interpreter, RuntimeError, Value},
+    token::Token,
+};
+
+pub fn define_natives(environment: &mut Environment) {
+    define_variadic(environment, "assert", 1, assert);
+    define(environment, "assert_throws", 1, assert_throws);
+    define(environment, "mod", 2, modulo);
+    define(environment, "len", 1, len);
+    define(environment, "substr", 3, substr);
+    define(environment, "charAt", 2, char_at);
+    define(environment, "type", 1, type_of);
+    define(environment, "number", 1, number);
+    define(environment, "str", 1, str);
+    define(environment, "read_line", 0, read_line);
+    define(environment, "write", 1, write);
+    define_variadic(environment, "format", 1, format);
+    define(environment, "sqrt", 1, sqrt);
+    define(environment, "abs", 1, abs);
+    define(environment, "floor", 1, floor);
+    define(environment, "ceil", 1, ceil);
+    define(environment, "round", 1, round);
+    define(environment, "pow", 2, pow);
+    define_variadic(environment, "min", 1, min);
+    define_variadic(environment, "max", 1, max);
+    define(environment, "random", 0, random);
+    define(environment, "randint", 2, randint);
+    define(environment, "seed", 1, seed);
+}
+
+fn define(environment: &mut Environment, name: &'static str, arity: usize, function: NativeFn) {
+    define_native(environment, name, Arity::Exact(arity), function);
+}
+
+fn define_variadic(
+    environment: &mut Environment,
+    name: &'static str,
+    min_arity: usize,
+    function: NativeFn,
+) {
+    define_native(environment, name, Arity::AtLeast(min_arity), function);
+}
+
+fn define_native(
+    environment: &mut Environment,
+    name: &'static str,
+    arity: Arity,
+    function: NativeFn,
+) {
+    environment.define(
+        name.to_string(),
+        Value::NativeFunction(NativeFunction {
+            name,
+            arity,
+            function,
+        }),
+    );
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::String(_) => "string",
+        Value::Number(_) => "number",
+        Value::Bool(_) => "boolean",
+        Value::Nil => "nil",
+        Value::Function(_) | Value::NativeFunction(_) => "function",
+        Value::List(_) => "list",
+        Value::Map(_) => "map",
+        Value::Class(_) => "class",
+        Value::Instance(_) => "instance",
+    }
+}
+
+fn string_argument<'a>(
+    interpreter: &Interpreter,
+    paren: &Token,
+    value: &'a Value,
+) -> Result<&'a str, Exit> {
+    match value {
+        Value::String(string) => Ok(string),
+        _ => Err(RuntimeError::new(
+            paren.line,
+            &format!(
+                "Argument must be a string, got {}.",
+                interpreter.describe(value)
+            ),
+        )
+        .into()),
+    }
+}
+
+fn number_argument(interpreter: &Interpreter, paren: &Token, value: &Value) -> Result<f64, Exit> {
+    match value {
+        Value::Number(number) => Ok(*number),
+        _ => Err(RuntimeError::new(
+            paren.line,
+            &format!(
+                "Argument must be a number, got {}.",
+                interpreter.describe(value)
+            ),
+        )
+        .into()),
+    }
+}
+
+fn index_argument(interpreter: &Interpreter, paren: &Token, value: &Value) -> Result<usize, Exit> {
+    match value {
+        Value::Number(number) if *number >= 0.0 && number.fract() == 0.0 => Ok(*number as usize),
+        _ => Err(RuntimeError::new(
+            paren.line,
+            &format!(
+                "Index must be a non-negative integer, got {}.",
+                interpreter.describe(value)
+            ),
+        )
+        .into()),
+    }
+}
+
+// `assert(condition)` or `assert(condition, message)`.
+fn assert(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    if arguments.len() > 2 {
+        return Err(RuntimeError::new(
+            paren.line,
+            &format!(
+                "Expected 1 or 2 arguments but got {} when calling 'assert'.",
+                arguments.len()
+            ),
+        )
+        .into());
+    }
+
+    if interpreter.is_truthy(&arguments[0]) {
+        return Ok(Value::Nil);
+    }
+    match arguments.get(1) {
+        Some(message) => {
+            let message = interpreter.stringify(message.clone(), paren.line)?;
+            Err(RuntimeError::new(paren.line, &format!("Assertion failed: {message}")).into())
+        }
+        None => Err(RuntimeError::new(paren.line, "Assertion failed.").into()),
+    }
+}
+
+fn assert_throws(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    let callee = arguments[0].clone();
+    match interpreter.call(callee, paren, Vec::new()) {
+        Err(Exit::RuntimeError(_)) => Ok(Value::Nil),
+        Err(exit) => Err(exit),
+        Ok(_) => {
+            Err(RuntimeError::new(paren.line, "Expected an error but none was raised.").into())
+        }
+    }
+}
+
+fn type_of(
+    _interpreter: &mut Interpreter,
+    _paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    Ok(Value::String(type_name(&arguments[0]).to_string()))
+}
+
+fn number(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    let parsed = match &arguments[0] {
+        Value::Number(number) => Some(*number),
+        Value::String(string) => string.trim().parse().ok(),
+        _ => None,
+    };
+
+    match parsed {
+        Some(number) => Ok(Value::Number(number)),
+        None => Err(RuntimeError::new(
+            paren.line,
+            &format!(
+                "Cannot convert {} to a number.",
+                interpreter.describe(&arguments[0])
+            ),
+        )
+        .into()),
+    }
+}
+
+fn str(interpreter: &mut Interpreter, paren: &Token, arguments: Vec<Value>) -> Result<Value, Exit> {
+    Ok(Value::String(
+        interpreter.stringify(arguments[0].clone(), paren.line)?,
+    ))
+}
+
+fn read_line(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    _arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    match interpreter.read_line() {
+        Ok(Some(line)) => Ok(Value::String(line)),
+        Ok(None) => Ok(Value::Nil),
+        Err(_) => Err(RuntimeError::new(paren.line, "Failed to read input.").into()),
+    }
+}
+
+// `{}` takes the next argument and `{{`/`}}` are literal braces.
+fn format(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    let template = string_argument(interpreter, paren, &arguments[0])?.to_string();
+    let mut values = arguments.into_iter().skip(1);
+    let mut output = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                output.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                let Some(value) = values.next() else {
+                    return Err(RuntimeError::new(
+                        paren.line,
+                        "Not enough arguments for format string.",
+                    )
+                    .into());
+                };
+                output.push_str(&interpreter.stringify(value, paren.line)?);
+            }
+            ('{', _) | ('}', _) => {
+                return Err(RuntimeError::new(
+                    paren.line,
+                    &format!("Unmatched '{}' in format string.", c),
+                )
+                .into())
+            }
+            _ => output.push(c),
+        }
+    }
+
+    if values.next().is_some() {
+        return Err(RuntimeError::new(paren.line, "Too many arguments for format string.").into());
+    }
+    Ok(Value::String(output))
+}
+
+fn write(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    let text = interpreter.stringify(arguments[0].clone(), paren.line)?;
+    interpreter.write_output(paren.line, &text)?;
+    Ok(Value::Nil)
+}
+
+fn modulo(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    match (&arguments[0], &arguments[1]) {
+        (Value::Number(_), Value::Number(b)) if *b == 0.0 => {
+            Err(RuntimeError::new(paren.line, "Modulo by zero.").into())
+        }
+        (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a - b * (a / b).floor())),
+        (a, b) => Err(RuntimeError::new(
+            paren.line,
+            &format!(
+                "Arguments must be numbers, got {} and {}.",
+                interpreter.describe(a),
+                interpreter.describe(b)
+            ),
+        )
+        .into()),
+    }
+}
+
+fn apply_math(
+    interpreter: &Interpreter,
+    paren: &Token,
+    arguments: &[Value],
+    operation: fn(f64) -> f64,
+) -> Result<Value, Exit> {
+    let number = number_argument(interpreter, paren, &arguments[0])?;
+    Ok(Value::Number(operation(number)))
+}
+
+fn sqrt(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    apply_math(interpreter, paren, &arguments, f64::sqrt)
+}
+
+fn abs(interpreter: &mut Interpreter, paren: &Token, arguments: Vec<Value>) -> Result<Value, Exit> {
+    apply_math(interpreter, paren, &arguments, f64::abs)
+}
+
+fn floor(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    apply_math(interpreter, paren, &arguments, f64::floor)
+}
+
+fn ceil(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    apply_math(interpreter, paren, &arguments, f64::ceil)
+}
+
+// Halfway values round away from zero, so `round(-2.5)` is -3.
+fn round(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    apply_math(interpreter, paren, &arguments, f64::round)
+}
+
+fn pow(interpreter: &mut Interpreter, paren: &Token, arguments: Vec<Value>) -> Result<Value, Exit> {
+    let base = number_argument(interpreter, paren, &arguments[0])?;
+    let exponent = number_argument(interpreter, paren, &arguments[1])?;
+    Ok(Value::Number(base.powf(exponent)))
+}
+
+fn min(interpreter: &mut Interpreter, paren: &Token, arguments: Vec<Value>) -> Result<Value, Exit> {
+    let mut min = f64::INFINITY;
+    for argument in arguments.iter() {
+        min = min.min(number_argument(interpreter, paren, argument)?);
+    }
+    Ok(Value::Number(min))
+}
+
+fn max(interpreter: &mut Interpreter, paren: &Token, arguments: Vec<Value>) -> Result<Value, Exit> {
+    let mut max = f64::NEG_INFINITY;
+    for argument in arguments.iter() {
+        max = max.max(number_argument(interpreter, paren, argument)?);
+    }
+    Ok(Value::Number(max))
+}
+
+fn random(
+    interpreter: &mut Interpreter,
+    _paren: &Token,
+    _arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    Ok(Value::Number(interpreter.next_random()))
+}
+
+// Both bounds are included, so `randint(1, 6)` rolls a die.
+fn randint(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    let low = number_argument(interpreter, paren, &arguments[0])?.ceil();
+    let high = number_argument(interpreter, paren, &arguments[1])?.floor();
+    if low > high {
+        return Err(RuntimeError::new(paren.line, "Empty range for randint.").into());
+    }
+
+    let draw = (interpreter.next_random() * (high - low + 1.0)).floor();
+    Ok(Value::Number(low + draw))
+}
+
+fn seed(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    let seed = number_argument(interpreter, paren, &arguments[0])?;
+    interpreter.seed(seed as i64 as u64);
+    Ok(Value::Nil)
+}
+
+fn len(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    let len = match &arguments[0] {
+        Value::String(string) => string.chars().count(),
+        Value::List(list) => list.borrow().len(),
+        Value::Map(map) => map.borrow().len(),
+        value => {
+            return Err(RuntimeError::new(
+                paren.line,
+                &format!("Cannot take length of {}.", type_name(value)),
+            )
+            .into())
+        }
+    };
+
+    Ok(Value::Number(len as f64))
+}
+
+fn substr(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    let string = string_argument(interpreter, paren, &arguments[0])?;
+    let start = index_argument(interpreter, paren, &arguments[1])?;
+    let len = index_argument(interpreter, paren, &arguments[2])?;
+    let chars = string.chars().count();
+    // Compared this way round, as huge indices would overflow `start + len`.
+    if start > chars || len > chars - start {
+        return Err(RuntimeError::new(
+            paren.line,
+            &format!(
+                "Substring {}..{} out of bounds for length {}.",
+                start,
+                start.saturating_add(len),
+                chars
+            ),
+        )
+        .into());
+    }
+
+    Ok(Value::String(
+        string.chars().skip(start).take(len).collect(),
+    ))
+}
+
+fn char_at(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, Exit> {
+    let string = string_argument(interpreter, paren, &arguments[0])?;
+    let index = index_argument(interpreter, paren, &arguments[1])?;
+    match string.chars().nth(index) {
+        Some(c) => Ok(Value::String(c.to_string())),
+        None => Err(RuntimeError::new(
+            paren.line,
+            &format!(
+                "String index {} out of bounds for length {}.",
+                index,
+                string.chars().count()
+            ),
+        )
+        .into()),
+    }
+}
@@ -0,0 +1,457 @@
+use std::mem;
+
+use crate::{
+    expr::{self, Expr, ExpressionVisitor},
+    interpreter::{Interpreter, Value},
+    stmt::{self, Comments, Stmt},
+    token::{LiteralKind, TokenKind},
+    transform::walk_mut,
+};
+
+/// Rewrites constant sub-expressions into the literal they evaluate to, so
+/// `1 + 2 * 3` becomes `7` and `!true` becomes `false`. Anything that touches
+/// a variable, a call or a property is rebuilt around its folded children but
+/// never evaluated itself.
+///
+/// Constants are evaluated by a private interpreter, so the folded value is
+/// exactly what `run` would have produced. An operation that fails, like
+/// `1 / 0` or `1 - "a"`, is left in place to fail at runtime instead.
+pub struct ConstantFolder {
+    interpreter: Interpreter,
+}
+
+impl Default for ConstantFolder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConstantFolder {
+    pub fn new() -> Self {
+        ConstantFolder {
+            interpreter: Interpreter::new(),
+        }
+    }
+
+    pub fn fold(&mut self, expr: &Expr) -> Expr {
+        expr.accept(self)
+    }
+
+    pub fn fold_statements(&mut self, statements: &mut [Stmt]) {
+        for statement in statements.iter_mut() {
+            self.fold_statement(statement);
+        }
+    }
+
+    fn fold_statement(&mut self, statement: &mut Stmt) {
+        match statement {
+            Stmt::Expression(stmt) => self.fold_in_place(&mut stmt.expression),
+            Stmt::Print(stmt) => {
+                for expression in stmt.expressions.iter_mut() {
+                    *expression = self.fold(expression);
+                }
+            }
+            Stmt::Var(stmt) => {
+                if let Some(initializer) = &mut stmt.initializer {
+                    self.fold_in_place(initializer);
+                }
+            }
+            Stmt::Block(stmt) => self.fold_statements(&mut stmt.statements),
+            Stmt::If(stmt) => {
+                self.fold_in_place(&mut stmt.condition);
+                self.fold_statement(&mut stmt.then_branch);
+                if let Some(else_branch) = &mut stmt.else_branch {
+                    self.fold_statement(else_branch);
+                }
+            }
+            Stmt::While(stmt) => {
+                self.fold_in_place(&mut stmt.condition);
+                self.fold_statement(&mut stmt.body);
+                if let Some(increment) = &mut stmt.increment {
+                    self.fold_in_place(increment);
+                }
+            }
+            Stmt::ForIn(stmt) => {
+                self.fold_in_place(&mut stmt.iterable);
+                self.fold_statement(&mut stmt.body);
+            }
+            Stmt::Function(stmt) => self.fold_statements(&mut stmt.body),
+            Stmt::Return(stmt) => {
+                if let Some(value) = &mut stmt.value {
+                    *value = self.fold(value);
+                }
+            }
+            Stmt::Class(stmt) => {
+                self.fold_statements(&mut stmt.methods);
+                self.fold_statements(&mut stmt.class_methods);
+            }
+            Stmt::Switch(stmt) => {
+                stmt.scrutinee = self.fold(&stmt.scrutinee);
+                for (patterns, body) in stmt.arms.iter_mut() {
+                    *patterns = self.fold_all(patterns);
+                    self.fold_statements(body);
+                }
+                if let Some(default) = &mut stmt.default {
+                    self.fold_statements(default);
+                }
+            }
+            Stmt::Break(stmt) => {
+                if let Some(value) = &mut stmt.value {
+                    *value = self.fold(value);
+                }
+            }
+            Stmt::Continue(_) => {}
+        }
+    }
+
+    fn fold_in_place(&mut self, expr: &mut Box<Expr>) {
+        **expr = self.fold(expr);
+    }
+
+    fn fold_box(&mut self, expr: &Expr) -> Box<Expr> {
+        Box::new(self.fold(expr))
+    }
+
+    fn fold_all(&mut self, exprs: &[Expr]) -> Vec<Expr> {
+        exprs.iter().map(|expr| self.fold(expr)).collect()
+    }
+
+    // Only operators whose operands are all literals are evaluated, and only
+    // a primitive result is kept.
+    fn evaluate(&mut self, expr: Expr, line: usize) -> Expr {
+        let value = match self.interpreter.evaluate(&expr) {
+            Ok(Value::Number(number)) => LiteralKind::Number(number),
+            Ok(Value::String(string)) => LiteralKind::String(string),
+            Ok(Value::Bool(bool)) => LiteralKind::Bool(bool),
+            Ok(Value::Nil) => LiteralKind::Nil,
+            _ => return expr,
+        };
+        Expr::Literal(expr::Literal { value, line })
+    }
+}
+
+/// Folds the constant expressions of every statement in place.
+///
+/// ```
+/// use codecrafters_interpreter::{
+///     formatter::Formatter, optimizer::fold_constants, parser::Parser, scanner::Scanner,
+/// };
+///
+/// let fold = |source: &str| {
+///     let (tokens, _) = Scanner::new(source.to_string()).into_parts();
+///     let mut statements = Parser::new(tokens).parse().unwrap();
+///     fold_constants(&mut statements);
+///     Formatter::new().format(&statements)
+/// };
+///
+/// assert_eq!(fold("print 1 + 2 * 3;"), "print 7;\n");
+/// assert_eq!(fold("print !true, \"a\" + \"b\";"), "print false, \"ab\";\n");
+/// assert_eq!(fold("print x * (2 + 3);"), "print x * 5;\n");
+/// assert_eq!(fold("print x + 1;"), "print x + 1;\n");
+/// // Left for the interpreter to report at runtime.
+/// assert_eq!(fold("print 1 / (2 - 2);"), "print 1 / 0;\n");
+/// ```
+pub fn fold_constants(statements: &mut [Stmt]) {
+    ConstantFolder::new().fold_statements(statements);
+}
+
+/// Drops the branches that a literal condition rules out: `if (false)` keeps
+/// only its else branch and `while (false)` disappears. Conditions are only
+/// looked at as written, so run [`fold_constants`] first to catch `if (1 > 2)`.
+///
+/// ```
+/// use codecrafters_interpreter::{
+///     formatter::Formatter, optimizer::eliminate_dead_code, parser::Parser, scanner::Scanner,
+/// };
+///
+/// let eliminate = |source: &str| {
+///     let (tokens, _) = Scanner::new(source.to_string()).into_parts();
+///     let mut statements = Parser::new(tokens).parse().unwrap();
+///     eliminate_dead_code(&mut statements);
+///     Formatter::new().format(&statements)
+/// };
+///
+/// assert_eq!(
+///     eliminate("if (false) print 1; else { print 2; }"),
+///     "{\n    print 2;\n}\n"
+/// );
+/// assert_eq!(eliminate("print 0; while (false) print 1;"), "print 0;\n");
+/// assert_eq!(
+///     eliminate("if (x) print 1; else print 2;"),
+///     "if (x)\n    print 1;\nelse\n    print 2;\n"
+/// );
+/// ```
+pub fn eliminate_dead_code(statements: &mut Vec<Stmt>) {
+    prune(statements);
+    walk_mut(statements, &mut |expr| match expr {
+        Expr::Lambda(lambda) => prune(&mut lambda.body),
+        Expr::Loop(loop_expr) => prune(&mut loop_expr.body),
+        Expr::Block(block) => prune(&mut block.statements),
+        _ => {}
+    });
+}
+
+fn prune(statements: &mut Vec<Stmt>) {
+    statements.retain_mut(prune_statement);
+}
+
+// A branch that can't be removed outright is left as an empty block.
+fn prune_branch(branch: &mut Stmt) {
+    if !prune_statement(branch) {
+        *branch = empty_block();
+    }
+}
+
+// Returns false when the statement can never run and should be dropped.
+fn prune_statement(statement: &mut Stmt) -> bool {
+    let survivor = match statement {
+        Stmt::Block(stmt) => {
+            prune(&mut stmt.statements);
+            return true;
+        }
+        Stmt::If(stmt) => {
+            prune_branch(&mut stmt.then_branch);
+            if let Some(else_branch) = &mut stmt.else_branch {
+                prune_branch(else_branch);
+            }
+            match truthiness(&stmt.condition) {
+                Some(true) => Some(mem::replace(&mut *stmt.then_branch, empty_block())),
+                Some(false) => match stmt.else_branch.take() {
+                    Some(else_branch) => Some(*else_branch),
+                    None => return false,
+                },
+                None => None,
+            }
+        }
+        Stmt::While(stmt) => {
+            prune_branch(&mut stmt.body);
+            if truthiness(&stmt.condition) == Some(false) {
+                return false;
+            }
+            None
+        }
+        Stmt::ForIn(stmt) => {
+            prune_branch(&mut stmt.body);
+            None
+        }
+        Stmt::Function(stmt) => {
+            prune(&mut stmt.body);
+            None
+        }
+        Stmt::Class(stmt) => {
+            prune(&mut stmt.methods);
+            prune(&mut stmt.class_methods);
+            None
+        }
+        Stmt::Switch(stmt) => {
+            for (_, body) in stmt.arms.iter_mut() {
+                prune(body);
+            }
+            if let Some(default) = &mut stmt.default {
+                prune(default);
+            }
+            None
+        }
+        _ => None,
+    };
+
+    if let Some(survivor) = survivor {
+        *statement = survivor;
+    }
+    true
+}
+
+fn truthiness(condition: &Expr) -> Option<bool> {
+    match condition {
+        Expr::Literal(literal) => Some(!matches!(
+            literal.value,
+            LiteralKind::Bool(false) | LiteralKind::Nil
+        )),
+        _ => None,
+    }
+}
+
+fn empty_block() -> Stmt {
+    Stmt::Block(stmt::Block {
+        statements: Vec::new(),
+        comments: Comments::default(),
+    })
+}
+
+impl ExpressionVisitor<Expr> for ConstantFolder {
+    fn visit_assignment(&mut self, expr: &expr::Assignment) -> Expr {
+        Expr::Assignment(expr::Assignment {
+            value: self.fold_box(&expr.value),
+            ..expr.clone()
+        })
+    }
+
+    fn visit_binary(&mut self, expr: &expr::Binary) -> Expr {
+        let left = self.fold_box(&expr.left);
+        let right = self.fold_box(&expr.right);
+        let constant = matches!(*left, Expr::Literal(_)) && matches!(*right, Expr::Literal(_));
+        let binary = Expr::Binary(expr::Binary {
+            left,
+            operator: expr.operator.clone(),
+            right,
+        });
+        match constant {
+            true => self.evaluate(binary, expr.operator.line),
+            false => binary,
+        }
+    }
+
+    fn visit_grouping(&mut self, expr: &expr::Grouping) -> Expr {
+        match self.fold(&expr.expr) {
+            literal @ Expr::Literal(_) => literal,
+            inner => Expr::Grouping(expr::Grouping {
+                expr: Box::new(inner),
+                line: expr.line,
+            }),
+        }
+    }
+
+    fn visit_literal(&self, expr: &expr::Literal) -> Expr {
+        Expr::Literal(expr.clone())
+    }
+
+    fn visit_logical(&mut self, expr: &expr::Logical) -> Expr {
+        let left = self.fold(&expr.left);
+        let right = self.fold(&expr.right);
+        // A constant left operand decides whether the right one is needed,
+        // even when the right one isn't constant.
+        if let Expr::Literal(literal) = &left {
+            let truthy = self.interpreter.is_truthy(&literal.value.clone().into());
+            match expr.operator.kind {
+                TokenKind::Or if truthy => return left,
+                TokenKind::And if !truthy => return left,
+                TokenKind::Or | TokenKind::And => return right,
+                _ => {}
+            }
+        }
+
+        let constant = matches!(left, Expr::Literal(_)) && matches!(right, Expr::Literal(_));
+        let logical = Expr::Logical(expr::Logical {
+            left: Box::new(left),
+            operator: expr.operator.clone(),
+            right: Box::new(right),
+        });
+        match constant {
+            true => self.evaluate(logical, expr.operator.line),
+            false => logical,
+        }
+    }
+
+    fn visit_unary(&mut self, expr: &expr::Unary) -> Expr {
+        let right = self.fold_box(&expr.right);
+        let constant = matches!(*right, Expr::Literal(_));
+        let unary = Expr::Unary(expr::Unary {
+            operator: expr.operator.clone(),
+            right,
+        });
+        match constant {
+            true => self.evaluate(unary, expr.operator.line),
+            false => unary,
+        }
+    }
+
+    fn visit_postfix(&mut self, expr: &expr::Postfix) -> Expr {
+        Expr::Postfix(expr.clone())
+    }
+
+    fn visit_variable(&mut self, expr: &expr::Variable) -> Expr {
+        Expr::Variable(expr.clone())
+    }
+
+    fn visit_call(&mut self, expr: &expr::Call) -> Expr {
+        Expr::Call(expr::Call {
+            callee: self.fold_box(&expr.callee),
+            paren: expr.paren.clone(),
+            arguments: self.fold_all(&expr.arguments),
+        })
+    }
+
+    fn visit_lambda(&mut self, expr: &expr::Lambda) -> Expr {
+        let mut lambda = expr.clone();
+        self.fold_statements(&mut lambda.body);
+        Expr::Lambda(lambda)
+    }
+
+    fn visit_list(&mut self, expr: &expr::List) -> Expr {
+        Expr::List(expr::List {
+            bracket: expr.bracket.clone(),
+            elements: self.fold_all(&expr.elements),
+        })
+    }
+
+    fn visit_map(&mut self, expr: &expr::Map) -> Expr {
+        Expr::Map(expr::Map {
+            brace: expr.brace.clone(),
+            entries: expr
+                .entries
+                .iter()
+                .map(|(key, value)| (self.fold(key), self.fold(value)))
+                .collect(),
+        })
+    }
+
+    fn visit_index(&mut self, expr: &expr::Index) -> Expr {
+        Expr::Index(expr::Index {
+            object: self.fold_box(&expr.object),
+            bracket: expr.bracket.clone(),
+            index: self.fold_box(&expr.index),
+        })
+    }
+
+    fn visit_set_index(&mut self, expr: &expr::SetIndex) -> Expr {
+        Expr::SetIndex(expr::SetIndex {
+            object: self.fold_box(&expr.object),
+            bracket: expr.bracket.clone(),
+            index: self.fold_box(&expr.index),
+            value: self.fold_box(&expr.value),
+        })
+    }
+
+    fn visit_get(&mut self, expr: &expr::Get) -> Expr {
+        Expr::Get(expr::Get {
+            object: self.fold_box(&expr.object),
+            name: expr.name.clone(),
+        })
+    }
+
+    fn visit_set(&mut self, expr: &expr::Set) -> Expr {
+        Expr::Set(expr::Set {
+            object: self.fold_box(&expr.object),
+            name: expr.name.clone(),
+            value: self.fold_box(&expr.value),
+        })
+    }
+
+    fn visit_this(&mut self, expr: &expr::This) -> Expr {
+        Expr::This(expr.clone())
+    }
+
+    fn visit_super(&mut self, expr: &expr::Super) -> Expr {
+        Expr::Super(expr.clone())
+    }
+
+    fn visit_loop(&mut self, expr: &expr::Loop) -> Expr {
+        let mut loop_expr = expr.clone();
+        self.fold_statements(&mut loop_expr.body);
+        Expr::Loop(loop_expr)
+    }
+
+    fn visit_block_expression(&mut self, expr: &expr::BlockExpression) -> Expr {
+        let mut block = expr.clone();
+        self.fold_statements(&mut block.statements);
+        block.value = block.value.map(|value| Box::new(value.accept(self)));
+        Expr::Block(block)
+    }
+
+    fn visit_interpolation(&mut self, expr: &expr::Interpolation) -> Expr {
+        Expr::Interpolation(expr::Interpolation {
+            parts: self.fold_all(&expr.parts),
+            line: expr.line,
+        })
+    }
+}
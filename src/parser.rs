@@ -1,28 +1,194 @@
+use std::{cell::Cell, collections::HashMap};
+
+use thiserror::Error;
+
 use crate::{
     expr::*,
     stmt::*,
     token::{LiteralKind, Token, TokenKind},
 };
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
+#[error("Failed to parse.")]
 pub struct ParserError;
 
 #[derive(Debug)]
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    loop_depth: usize,
+    function_depth: usize,
+    // Whether the innermost loop is a `loop` expression, whose `break` may
+    // carry a value.
+    in_loop_expression: bool,
+    depth: usize,
+    max_depth: usize,
+    max_chain: usize,
+    trivia: bool,
+    repl: bool,
+    comments: HashMap<usize, Vec<Token>>,
 }
 
 impl Parser {
+    /// Takes the tokens by value, so a scanner can hand them over without a copy.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::{parser::Parser, scanner::Scanner};
+    ///
+    /// let source: String = (0..20_000)
+    ///     .map(|i| format!("var x{} = {} * 2;\nprint x{};\n", i, i, i))
+    ///     .collect();
+    /// let tokens = Scanner::new(source).into_tokens().unwrap();
+    /// assert_eq!(tokens.len(), 20_000 * 10 + 1);
+    ///
+    /// let statements = Parser::new(tokens).parse().unwrap();
+    /// assert_eq!(statements.len(), 40_000);
+    /// ```
     pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+        let mut comments: HashMap<usize, Vec<Token>> = HashMap::new();
+        let mut code = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            match token.kind {
+                TokenKind::Comment => comments.entry(code.len()).or_default().push(token),
+                _ => code.push(token),
+            }
+        }
+
+        Parser {
+            tokens: code,
+            current: 0,
+            loop_depth: 0,
+            function_depth: 0,
+            in_loop_expression: false,
+            depth: 0,
+            max_depth: 128,
+            max_chain: 100_000,
+            trivia: false,
+            repl: false,
+            comments,
+        }
+    }
+
+    /// Attaches the comments on the lines above each declaration, and those
+    /// after it on its last line, to it. The scanner has to keep comments for
+    /// there to be any.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::{parser::Parser, scanner::Scanner, stmt::Stmt};
+    ///
+    /// let source = "var a = 1;\n// show a\nprint a; // twice\n";
+    /// let tokens = Scanner::new(source.to_string())
+    ///     .with_comments(true)
+    ///     .scan_tokens()
+    ///     .clone();
+    /// let statements = Parser::new(tokens).with_trivia(true).parse().unwrap();
+    ///
+    /// assert!(statements[0].comments().is_empty());
+    /// let Stmt::Print(print) = &statements[1] else {
+    ///     panic!("expected a print statement");
+    /// };
+    /// assert_eq!(print.comments.leading, vec!["// show a"]);
+    /// assert_eq!(print.comments.trailing, vec!["// twice"]);
+    /// ```
+    pub fn with_trivia(mut self, trivia: bool) -> Self {
+        self.trivia = trivia;
+        self
+    }
+
+    /// Lets the last expression statement of the source leave out its ';',
+    /// as a line typed at the REPL ends with the input. Files keep requiring
+    /// it.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::{parser::Parser, scanner::Scanner};
+    ///
+    /// let parses = |source: &str, repl: bool| {
+    ///     let (tokens, _) = Scanner::new(source.to_string()).into_parts();
+    ///     Parser::new(tokens).with_repl(repl).parse().is_ok()
+    /// };
+    ///
+    /// assert!(parses("1 + 2", true));
+    /// assert!(!parses("1 + 2", false));
+    /// assert!(!parses("1 + 2 print 3;", true));
+    /// ```
+    pub fn with_repl(mut self, repl: bool) -> Self {
+        self.repl = repl;
+        self
+    }
+
+    /// Limits how deeply expressions and statements may nest before parsing
+    /// gives up with "Too much nesting." instead of overflowing the stack.
+    /// Parentheses, unary operators, chained assignments and blocks count;
+    /// the arms of an `else if` chain follow one another and don't.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::{
+    ///     diagnostic::Diagnostic, parser::Parser, scanner::Scanner, take_diagnostics,
+    /// };
+    ///
+    /// let parse = |source: String| {
+    ///     let (tokens, _) = Scanner::new(source).into_parts();
+    ///     let parsed = Parser::new(tokens).parse();
+    ///     (parsed.is_ok(), take_diagnostics())
+    /// };
+    ///
+    /// let parens = format!("print {}1{};", "(".repeat(10_000), ")".repeat(10_000));
+    /// assert_eq!(
+    ///     parse(parens),
+    ///     (false, vec![Diagnostic::error(1, "at '(': Too much nesting.")])
+    /// );
+    ///
+    /// let assignments = format!("var a;\n{}1;", "a = ".repeat(20_000));
+    /// assert_eq!(
+    ///     parse(assignments),
+    ///     (false, vec![Diagnostic::error(2, "at 'a': Too much nesting.")])
+    /// );
+    ///
+    /// assert_eq!(parse(format!("print {}1{};", "(".repeat(100), ")".repeat(100))), (true, vec![]));
+    ///
+    /// let arms: String = (1..1000).map(|i| format!(" else if (x == {i}) print {i};")).collect();
+    /// assert_eq!(parse(format!("var x = 0;\nif (x == 0) print 0;{arms}")), (true, vec![]));
+    /// ```
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Limits how many operators, calls, indexes and property accesses may
+    /// be chained one after another, as in `1 + 1 + 1`, before parsing gives
+    /// up with "Expression too long.". Each one nests the tree a level
+    /// deeper for every later pass to walk.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::{
+    ///     diagnostic::Diagnostic, parser::Parser, scanner::Scanner, take_diagnostics,
+    /// };
+    ///
+    /// let parse = |source: String, max_chain: usize| {
+    ///     let (tokens, _) = Scanner::new(source).into_parts();
+    ///     let parsed = Parser::new(tokens).with_max_chain(max_chain).parse();
+    ///     (parsed.is_ok(), take_diagnostics())
+    /// };
+    ///
+    /// let sum = format!("print 1{};", "+1".repeat(100_000));
+    /// assert_eq!(
+    ///     parse(sum, 1024),
+    ///     (false, vec![Diagnostic::error(1, "at '+': Expression too long.")])
+    /// );
+    ///
+    /// assert_eq!(parse("print 1 + 2 + 3;".to_string(), 2), (true, vec![]));
+    /// assert!(!parse("print f()()();".to_string(), 2).0);
+    /// ```
+    pub fn with_max_chain(mut self, max_chain: usize) -> Self {
+        self.max_chain = max_chain;
+        self
     }
 
     pub fn parse(&mut self) -> Result<Vec<Stmt>, ParserError> {
         let mut statements = Vec::new();
         let mut has_error = false;
-        while !self.is_at_end() {
-            match self.declaration() {
+        while let Some(statement) = self.next_statement() {
+            match statement {
                 Ok(statement) => statements.push(statement),
                 Err(_) => has_error = true,
             }
@@ -34,6 +200,46 @@ impl Parser {
         }
     }
 
+    /// Parses one top-level declaration, or returns `None` once the tokens
+    /// run out. After an error the parser has already synchronized, so the
+    /// caller can keep asking for statements.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::{
+    ///     interpreter::{Interpreter, Value},
+    ///     parser::Parser,
+    ///     scanner::Scanner,
+    /// };
+    ///
+    /// let parser = |source: &str| {
+    ///     let (tokens, _) = Scanner::new(source.to_string()).into_parts();
+    ///     Parser::new(tokens)
+    /// };
+    /// let a = parser("a").parse_expression().unwrap();
+    ///
+    /// let mut parser = parser("var a = 1;\na = a + ;\na = a + 1;");
+    /// let mut interpreter = Interpreter::new();
+    /// let mut seen = Vec::new();
+    /// while let Some(statement) = parser.next_statement() {
+    ///     let Ok(statement) = statement else {
+    ///         seen.push(None);
+    ///         continue;
+    ///     };
+    ///     interpreter.interpret_statement(&statement).unwrap();
+    ///     seen.push(interpreter.evaluate_expression(&a).ok());
+    /// }
+    /// assert_eq!(
+    ///     seen,
+    ///     vec![Some(Value::Number(1.0)), None, Some(Value::Number(2.0))]
+    /// );
+    /// ```
+    pub fn next_statement(&mut self) -> Option<Result<Stmt, ParserError>> {
+        match self.is_at_end() {
+            true => None,
+            false => Some(self.declaration()),
+        }
+    }
+
     pub fn parse_expression(&mut self) -> Result<Expr, ParserError> {
         match self.assignment() {
             Ok(expr) => Ok(expr),
@@ -42,18 +248,58 @@ impl Parser {
     }
 
     fn expression(&mut self) -> Result<Expr, ParserError> {
-        self.assignment()
+        self.nested(Self::assignment)
+    }
+
+    // Counts one more link in an operator or call chain.
+    fn link(&mut self, links: &mut usize) -> Result<(), ParserError> {
+        *links += 1;
+        if *links > self.max_chain {
+            self.error(&self.previous(), "Expression too long.");
+            return Err(ParserError);
+        }
+        Ok(())
+    }
+
+    fn nested<T>(
+        &mut self,
+        rule: impl FnOnce(&mut Self) -> Result<T, ParserError>,
+    ) -> Result<T, ParserError> {
+        if self.depth >= self.max_depth {
+            self.error(self.peek(), "Too much nesting.");
+            return Err(ParserError);
+        }
+
+        self.depth += 1;
+        let result = rule(self);
+        self.depth -= 1;
+        result
     }
 
     fn declaration(&mut self) -> Result<Stmt, ParserError> {
-        let statement = if self.token_match(&[TokenKind::Var]) {
+        if self.skip_stray_delimiter() {
+            return Err(ParserError);
+        }
+
+        let leading = self.leading_comments();
+        let statement = if self.token_match(&[TokenKind::Class]) {
+            self.class_declaration()
+        } else if self.check(&TokenKind::Fun) && self.check_next(&TokenKind::Identifier) {
+            self.advance();
+            self.function("function")
+        } else if self.token_match(&[TokenKind::Var]) {
             self.var_declaration()
+        } else if self.token_match(&[TokenKind::Const]) {
+            self.const_declaration()
         } else {
             self.statement()
         };
 
-        match &statement {
-            Ok(_) => statement,
+        match statement {
+            Ok(mut statement) => {
+                self.attach_comments(&mut statement, leading);
+                Ok(statement)
+            }
             Err(_) => {
                 self.synchronize();
                 Err(ParserError)
@@ -61,7 +307,56 @@ impl Parser {
         }
     }
 
+    fn class_declaration(&mut self) -> Result<Stmt, ParserError> {
+        let name = self.consume(TokenKind::Identifier, "Expect class name.")?;
+        let super_class = if self.token_match(&[TokenKind::Less]) {
+            let name = self.consume(TokenKind::Identifier, "Expect superclass name.")?;
+            Some(Expr::Variable(Variable {
+                name,
+                resolution: Cell::default(),
+            }))
+        } else {
+            None
+        };
+
+        self.consume(TokenKind::LeftBrace, "Expect '{' before class body.")?;
+        let mut methods = Vec::new();
+        let mut class_methods = Vec::new();
+        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+            let leading = self.leading_comments();
+            let is_class_method = self.token_match(&[TokenKind::Class]);
+            let mut method = self.function("method")?;
+            self.attach_comments(&mut method, leading);
+            match is_class_method {
+                true => class_methods.push(method),
+                false => methods.push(method),
+            }
+        }
+        self.consume(TokenKind::RightBrace, "Expect '}' after class body.")?;
+
+        Ok(Stmt::Class(Class {
+            name,
+            super_class,
+            methods,
+            class_methods,
+            comments: Comments::default(),
+        }))
+    }
+
+    /// Counts against the nesting limit, so deeply nested blocks are rejected
+    /// here rather than overflowing the stack in a later pass. Statements
+    /// that follow one another don't nest.
     fn statement(&mut self) -> Result<Stmt, ParserError> {
+        self.nested(Self::bare_statement)
+    }
+
+    fn bare_statement(&mut self) -> Result<Stmt, ParserError> {
+        if self.token_match(&[TokenKind::Break]) {
+            return self.break_statement();
+        }
+        if self.token_match(&[TokenKind::Continue]) {
+            return self.continue_statement();
+        }
         if self.token_match(&[TokenKind::For]) {
             return self.for_statement();
         }
@@ -71,12 +366,19 @@ impl Parser {
         if self.token_match(&[TokenKind::Print]) {
             return self.print_statement();
         }
+        if self.token_match(&[TokenKind::Return]) {
+            return self.return_statement();
+        }
+        if self.token_match(&[TokenKind::Switch]) {
+            return self.switch_statement();
+        }
         if self.token_match(&[TokenKind::While]) {
             return self.while_statement();
         }
         if self.token_match(&[TokenKind::LeftBrace]) {
             return Ok(Stmt::Block(Block {
                 statements: self.block()?,
+                comments: Comments::default(),
             }));
         }
         self.expression_statement()
@@ -84,6 +386,10 @@ impl Parser {
 
     fn for_statement(&mut self) -> Result<Stmt, ParserError> {
         self.consume(TokenKind::LeftParenthesis, "Expect '(' after 'for'.")?;
+        if self.check(&TokenKind::Identifier) && self.check_next(&TokenKind::In) {
+            return self.for_in_statement();
+        }
+
         let initializer = if self.token_match(&[TokenKind::Semicolon]) {
             None
         } else if self.token_match(&[TokenKind::Var]) {
@@ -97,6 +403,7 @@ impl Parser {
         } else {
             Expr::Literal(Literal {
                 value: LiteralKind::Bool(true),
+                line: self.peek().line,
             })
         };
         self.consume(TokenKind::Semicolon, "Expect ';' after loop condition.")?;
@@ -108,32 +415,39 @@ impl Parser {
         };
         self.consume(TokenKind::RightParenthesis, "Expect ')' after for clauses.")?;
 
-        let mut body = self.statement()?;
-        if let Some(increment) = increment {
-            body = Stmt::Block(Block {
-                statements: Vec::from([
-                    body,
-                    Stmt::Expression(Expression {
-                        expression: Box::new(increment),
-                    }),
-                ]),
-            });
-        };
-
-        body = Stmt::While(While {
+        let body = self.loop_body()?;
+        let mut body = Stmt::While(While {
             condition: Box::new(condition),
             body: Box::new(body),
+            increment: increment.map(Box::new),
+            comments: Comments::default(),
         });
 
         if let Some(initializer) = initializer {
             body = Stmt::Block(Block {
                 statements: Vec::from([initializer, body]),
+                comments: Comments::default(),
             })
         }
 
         Ok(body)
     }
 
+    fn for_in_statement(&mut self) -> Result<Stmt, ParserError> {
+        let variable = self.consume(TokenKind::Identifier, "Expect loop variable name.")?;
+        self.consume(TokenKind::In, "Expect 'in' after loop variable.")?;
+        let iterable = self.expression()?;
+        self.consume(TokenKind::RightParenthesis, "Expect ')' after for clauses.")?;
+        let body = self.loop_body()?;
+
+        Ok(Stmt::ForIn(ForIn {
+            variable,
+            iterable: Box::new(iterable),
+            body: Box::new(body),
+            comments: Comments::default(),
+        }))
+    }
+
     fn if_statement(&mut self) -> Result<Stmt, ParserError> {
         self.consume(TokenKind::LeftParenthesis, "Expect '(' after 'if'.")?;
         let condition = self.expression()?;
@@ -143,34 +457,130 @@ impl Parser {
         )?;
 
         let then_branch = self.statement()?;
-        let else_branch = if self.token_match(&[TokenKind::Else]) {
-            Some(self.statement()?)
-        } else {
-            None
-        };
 
-        Ok(Stmt::If(If {
-            condition: Box::new(condition),
-            then_branch: Box::new(then_branch),
-            else_branch: else_branch.map(Box::new),
+        // Each `else if` arm is read in this loop rather than by recursing, so
+        // a long chain doesn't count against the nesting limit.
+        let mut arms = vec![(condition, then_branch)];
+        let mut else_branch = None;
+        while self.token_match(&[TokenKind::Else]) {
+            if !self.token_match(&[TokenKind::If]) {
+                else_branch = Some(self.statement()?);
+                break;
+            }
+            self.consume(TokenKind::LeftParenthesis, "Expect '(' after 'if'.")?;
+            let condition = self.expression()?;
+            self.consume(
+                TokenKind::RightParenthesis,
+                "Expect ')' after if condition.",
+            )?;
+            arms.push((condition, self.statement()?));
+        }
+
+        let mut statement = else_branch;
+        for (condition, then_branch) in arms.into_iter().rev() {
+            statement = Some(Stmt::If(If {
+                condition: Box::new(condition),
+                then_branch: Box::new(then_branch),
+                else_branch: statement.map(Box::new),
+                comments: Comments::default(),
+            }));
+        }
+        Ok(statement.expect("an if statement has at least one arm"))
+    }
+
+    fn switch_statement(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous();
+        self.consume(TokenKind::LeftParenthesis, "Expect '(' after 'switch'.")?;
+        let scrutinee = self.expression()?;
+        self.consume(
+            TokenKind::RightParenthesis,
+            "Expect ')' after switch value.",
+        )?;
+        self.consume(TokenKind::LeftBrace, "Expect '{' before switch cases.")?;
+
+        let mut arms = Vec::new();
+        let mut default = None;
+        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+            if self.token_match(&[TokenKind::Case]) {
+                let mut patterns = vec![self.expression()?];
+                while self.token_match(&[TokenKind::Comma]) {
+                    patterns.push(self.expression()?);
+                }
+                self.consume(TokenKind::Colon, "Expect ':' after case value.")?;
+                arms.push((patterns, self.switch_arm()?));
+            } else if self.token_match(&[TokenKind::Default]) {
+                let token = self.previous();
+                if default.is_some() {
+                    self.error(&token, "A switch can only have one 'default'.");
+                    return Err(ParserError);
+                }
+                self.consume(TokenKind::Colon, "Expect ':' after 'default'.")?;
+                default = Some(self.switch_arm()?);
+            } else {
+                self.error(self.peek(), "Expect 'case' or 'default'.");
+                return Err(ParserError);
+            }
+        }
+        self.consume(TokenKind::RightBrace, "Expect '}' after switch cases.")?;
+
+        Ok(Stmt::Switch(Switch {
+            keyword,
+            scrutinee,
+            arms,
+            default,
+            comments: Comments::default(),
         }))
     }
 
+    fn switch_arm(&mut self) -> Result<Vec<Stmt>, ParserError> {
+        let mut statements = Vec::new();
+        while !self.check(&TokenKind::Case)
+            && !self.check(&TokenKind::Default)
+            && !self.check(&TokenKind::RightBrace)
+            && !self.is_at_end()
+        {
+            statements.push(self.declaration()?);
+        }
+        Ok(statements)
+    }
+
     fn print_statement(&mut self) -> Result<Stmt, ParserError> {
-        let value = self.expression()?;
+        let mut expressions = vec![self.expression()?];
+        while self.token_match(&[TokenKind::Comma]) {
+            expressions.push(self.expression()?);
+        }
         self.consume(TokenKind::Semicolon, "Expect ';' after value.")?;
         Ok(Stmt::Print(Print {
-            expression: Box::new(value),
+            expressions,
+            comments: Comments::default(),
+        }))
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous();
+        if self.function_depth == 0 {
+            self.error(&keyword, "Can't return from top-level code.");
+            return Err(ParserError);
+        }
+
+        let value = if !self.check(&TokenKind::Semicolon) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.consume(TokenKind::Semicolon, "Expect ';' after return value.")?;
+        Ok(Stmt::Return(Return {
+            keyword,
+            value,
+            comments: Comments::default(),
         }))
     }
 
     fn var_declaration(&mut self) -> Result<Stmt, ParserError> {
         let name = self.consume(TokenKind::Identifier, "Expect variable name.")?;
-        let mut initializer = Expr::Literal(Literal {
-            value: LiteralKind::Nil,
-        });
+        let mut initializer = None;
         if self.token_match(&[TokenKind::Equal]) {
-            initializer = self.expression()?;
+            initializer = Some(self.expression()?);
         }
         self.consume(
             TokenKind::Semicolon,
@@ -178,7 +588,25 @@ impl Parser {
         )?;
         Ok(Stmt::Var(Var {
             name,
-            initializer: Box::new(initializer),
+            initializer: initializer.map(Box::new),
+            constant: false,
+            comments: Comments::default(),
+        }))
+    }
+
+    fn const_declaration(&mut self) -> Result<Stmt, ParserError> {
+        let name = self.consume(TokenKind::Identifier, "Expect constant name.")?;
+        self.consume(TokenKind::Equal, "Expect '=' after constant name.")?;
+        let initializer = self.expression()?;
+        self.consume(
+            TokenKind::Semicolon,
+            "Expect ';' after constant declaration.",
+        )?;
+        Ok(Stmt::Var(Var {
+            name,
+            initializer: Some(Box::new(initializer)),
+            constant: true,
+            comments: Comments::default(),
         }))
     }
 
@@ -186,18 +614,159 @@ impl Parser {
         self.consume(TokenKind::LeftParenthesis, "Expect '(' after 'while'.")?;
         let condition = self.expression()?;
         self.consume(TokenKind::RightParenthesis, "Expect ')' after condition.")?;
-        let body = self.statement()?;
+        let body = self.loop_body()?;
         Ok(Stmt::While(While {
             condition: Box::new(condition),
             body: Box::new(body),
+            increment: None,
+            comments: Comments::default(),
+        }))
+    }
+
+    fn loop_body(&mut self) -> Result<Stmt, ParserError> {
+        let enclosing_loop_expression = self.in_loop_expression;
+        self.loop_depth += 1;
+        self.in_loop_expression = false;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        self.in_loop_expression = enclosing_loop_expression;
+        body
+    }
+
+    fn break_statement(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous();
+        if self.loop_depth == 0 {
+            self.error(&keyword, "Can't use 'break' outside of a loop.");
+            return Err(ParserError);
+        }
+
+        let mut value = None;
+        if !self.check(&TokenKind::Semicolon) {
+            if !self.in_loop_expression {
+                self.error(
+                    &keyword,
+                    "Can only break with a value out of a 'loop' expression.",
+                );
+                return Err(ParserError);
+            }
+            value = Some(self.expression()?);
+        }
+        self.consume(TokenKind::Semicolon, "Expect ';' after 'break'.")?;
+        Ok(Stmt::Break(Break {
+            keyword,
+            value,
+            comments: Comments::default(),
+        }))
+    }
+
+    fn continue_statement(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous();
+        if self.loop_depth == 0 {
+            self.error(&keyword, "Can't use 'continue' outside of a loop.");
+            return Err(ParserError);
+        }
+        self.consume(TokenKind::Semicolon, "Expect ';' after 'continue'.")?;
+        Ok(Stmt::Continue(Continue {
+            keyword,
+            comments: Comments::default(),
         }))
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, ParserError> {
         let expr = self.expression()?;
-        self.consume(TokenKind::Semicolon, "Expect ';' after expression.")?;
+        if !(self.repl && self.is_at_end()) {
+            self.consume(TokenKind::Semicolon, "Expect ';' after expression.")?;
+        }
         Ok(Stmt::Expression(Expression {
             expression: Box::new(expr),
+            comments: Comments::default(),
+        }))
+    }
+
+    fn function(&mut self, kind: &str) -> Result<Stmt, ParserError> {
+        let name = self.consume(TokenKind::Identifier, &format!("Expect {kind} name."))?;
+
+        // A method written without a parameter list is a getter.
+        if kind == "method" && self.token_match(&[TokenKind::LeftBrace]) {
+            return Ok(Stmt::Function(Function {
+                name,
+                params: Vec::new(),
+                variadic: false,
+                getter: true,
+                body: self.function_block()?,
+                comments: Comments::default(),
+            }));
+        }
+
+        self.consume(
+            TokenKind::LeftParenthesis,
+            &format!("Expect '(' after {kind} name."),
+        )?;
+        let (params, variadic, body) = self.function_body(kind)?;
+        Ok(Stmt::Function(Function {
+            name,
+            params,
+            variadic,
+            getter: false,
+            body,
+            comments: Comments::default(),
+        }))
+    }
+
+    // A trailing `...name` parameter collects any extra arguments into a list.
+    fn function_body(&mut self, kind: &str) -> Result<(Vec<Token>, bool, Vec<Stmt>), ParserError> {
+        let mut params = Vec::new();
+        let mut variadic = false;
+        if !self.check(&TokenKind::RightParenthesis) {
+            loop {
+                if params.len() >= 255 {
+                    self.error(self.peek(), "Can't have more than 255 parameters.");
+                    return Err(ParserError);
+                }
+                variadic = self.token_match(&[TokenKind::DotDotDot]);
+                params.push(self.consume(TokenKind::Identifier, "Expect parameter name.")?);
+                if !self.token_match(&[TokenKind::Comma]) {
+                    break;
+                }
+                if variadic {
+                    self.error(&self.previous(), "Rest parameter must be last.");
+                    return Err(ParserError);
+                }
+            }
+        }
+        self.consume(TokenKind::RightParenthesis, "Expect ')' after parameters.")?;
+        self.consume(
+            TokenKind::LeftBrace,
+            &format!("Expect '{{' before {kind} body."),
+        )?;
+        Ok((params, variadic, self.function_block()?))
+    }
+
+    fn function_block(&mut self) -> Result<Vec<Stmt>, ParserError> {
+        let enclosing_loop_depth = self.loop_depth;
+        let enclosing_loop_expression = self.in_loop_expression;
+        self.loop_depth = 0;
+        self.in_loop_expression = false;
+        self.function_depth += 1;
+        let body = self.block();
+        self.function_depth -= 1;
+        self.loop_depth = enclosing_loop_depth;
+        self.in_loop_expression = enclosing_loop_expression;
+        body
+    }
+
+    fn loop_expression(&mut self) -> Result<Expr, ParserError> {
+        let keyword = self.previous();
+        self.consume(TokenKind::LeftBrace, "Expect '{' after 'loop'.")?;
+        let enclosing_loop_expression = self.in_loop_expression;
+        self.loop_depth += 1;
+        self.in_loop_expression = true;
+        let body = self.block();
+        self.loop_depth -= 1;
+        self.in_loop_expression = enclosing_loop_expression;
+        Ok(Expr::Loop(Loop {
+            keyword,
+            body: body?,
         }))
     }
 
@@ -215,15 +784,34 @@ impl Parser {
 
         if self.token_match(&[TokenKind::Equal]) {
             let equals = self.previous();
-            let value = self.assignment()?;
-            if let Expr::Variable(variable) = expr {
-                return Ok(Expr::Assignment(Assignment {
-                    name: variable.name,
-                    value: Box::new(value),
-                }));
-            } else {
-                self.error(&equals, "Invalid assignment target.");
-                return Err(ParserError);
+            let value = self.nested(Self::assignment)?;
+            match expr {
+                Expr::Variable(variable) => {
+                    return Ok(Expr::Assignment(Assignment {
+                        name: variable.name,
+                        value: Box::new(value),
+                        resolution: Cell::default(),
+                    }))
+                }
+                Expr::Get(get) => {
+                    return Ok(Expr::Set(Set {
+                        object: get.object,
+                        name: get.name,
+                        value: Box::new(value),
+                    }))
+                }
+                Expr::Index(index) => {
+                    return Ok(Expr::SetIndex(SetIndex {
+                        object: index.object,
+                        bracket: index.bracket,
+                        index: index.index,
+                        value: Box::new(value),
+                    }))
+                }
+                _ => {
+                    self.error(&equals, "Invalid assignment target.");
+                    return Err(ParserError);
+                }
             }
         }
 
@@ -232,8 +820,10 @@ impl Parser {
 
     fn or(&mut self) -> Result<Expr, ParserError> {
         let mut expr = self.and()?;
-        while self.token_match(&[TokenKind::Or]) {
+        let mut links = 0;
+        while self.token_match(&[TokenKind::Or, TokenKind::Xor]) {
             let operator = self.previous();
+            self.link(&mut links)?;
             let right = self.and()?;
             expr = Expr::Logical(Logical {
                 left: Box::new(expr),
@@ -247,8 +837,10 @@ impl Parser {
 
     fn and(&mut self) -> Result<Expr, ParserError> {
         let mut expr = self.equality()?;
+        let mut links = 0;
         while self.token_match(&[TokenKind::And]) {
             let operator = self.previous();
+            self.link(&mut links)?;
             let right = self.equality()?;
             expr = Expr::Logical(Logical {
                 left: Box::new(expr),
@@ -262,8 +854,10 @@ impl Parser {
 
     fn equality(&mut self) -> Result<Expr, ParserError> {
         let mut expr = self.comparison();
+        let mut links = 0;
         while self.token_match(&[TokenKind::BangEqual, TokenKind::EqualEqual]) {
             let operator = self.previous();
+            self.link(&mut links)?;
             let right = self.comparison()?;
             expr = Ok(Expr::Binary(Binary {
                 left: Box::new(expr?),
@@ -277,13 +871,16 @@ impl Parser {
 
     fn comparison(&mut self) -> Result<Expr, ParserError> {
         let mut expr = self.term();
+        let mut links = 0;
         while self.token_match(&[
             TokenKind::Greater,
             TokenKind::GreaterEqual,
             TokenKind::Less,
             TokenKind::LessEqual,
+            TokenKind::Is,
         ]) {
             let operator = self.previous();
+            self.link(&mut links)?;
             let right = self.term()?;
             expr = Ok(Expr::Binary(Binary {
                 left: Box::new(expr?),
@@ -297,8 +894,10 @@ impl Parser {
 
     fn term(&mut self) -> Result<Expr, ParserError> {
         let mut expr = self.factor();
+        let mut links = 0;
         while self.token_match(&[TokenKind::Minus, TokenKind::Plus]) {
             let operator = self.previous();
+            self.link(&mut links)?;
             let right = self.factor()?;
             expr = Ok(Expr::Binary(Binary {
                 left: Box::new(expr?),
@@ -312,8 +911,10 @@ impl Parser {
 
     fn factor(&mut self) -> Result<Expr, ParserError> {
         let mut expr = self.unary();
+        let mut links = 0;
         while self.token_match(&[TokenKind::Slash, TokenKind::Star]) {
             let operator = self.previous();
+            self.link(&mut links)?;
             let right = self.unary()?;
             expr = Ok(Expr::Binary(Binary {
                 left: Box::new(expr?),
@@ -328,14 +929,90 @@ impl Parser {
     fn unary(&mut self) -> Result<Expr, ParserError> {
         if self.token_match(&[TokenKind::Bang, TokenKind::Minus]) {
             let operator = self.previous();
-            let right = self.unary()?;
+            let right = self.nested(Self::unary)?;
             return Ok(Expr::Unary(Unary {
                 operator,
                 right: Box::new(right),
             }));
         }
 
-        self.primary()
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr, ParserError> {
+        let expr = self.call()?;
+        if !self.token_match(&[TokenKind::PlusPlus, TokenKind::MinusMinus]) {
+            return Ok(expr);
+        }
+
+        let operator = self.previous();
+        match expr {
+            Expr::Variable(_) => Ok(Expr::Postfix(Postfix {
+                target: Box::new(expr),
+                operator,
+            })),
+            _ => {
+                self.error(&operator, "Invalid increment target.");
+                Err(ParserError)
+            }
+        }
+    }
+
+    fn call(&mut self) -> Result<Expr, ParserError> {
+        let mut expr = self.primary()?;
+        let mut links = 0;
+        loop {
+            if self.token_match(&[TokenKind::LeftParenthesis]) {
+                self.link(&mut links)?;
+                expr = self.finish_call(expr)?;
+            } else if self.token_match(&[TokenKind::LeftBracket]) {
+                self.link(&mut links)?;
+                let bracket = self.previous();
+                let index = self.expression()?;
+                self.consume(TokenKind::RightBracket, "Expect ']' after index.")?;
+                expr = Expr::Index(Index {
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                });
+            } else if self.token_match(&[TokenKind::Dot]) {
+                self.link(&mut links)?;
+                let name =
+                    self.consume(TokenKind::Identifier, "Expect property name after '.'.")?;
+                expr = Expr::Get(Get {
+                    object: Box::new(expr),
+                    name,
+                });
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    /// Calls take at most 255 arguments, as functions take at most 255
+    /// parameters.
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParserError> {
+        let mut arguments = Vec::new();
+        if !self.check(&TokenKind::RightParenthesis) {
+            loop {
+                if arguments.len() >= 255 {
+                    self.error(self.peek(), "Can't have more than 255 arguments.");
+                    return Err(ParserError);
+                }
+                arguments.push(self.expression()?);
+                if !self.token_match(&[TokenKind::Comma]) {
+                    break;
+                }
+            }
+        }
+        let paren = self.consume(TokenKind::RightParenthesis, "Expect ')' after arguments.")?;
+        Ok(Expr::Call(Call {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        }))
     }
 
     fn primary(&mut self) -> Result<Expr, ParserError> {
@@ -344,62 +1021,218 @@ impl Parser {
                 self.advance();
                 Ok(Expr::Literal(Literal {
                     value: LiteralKind::Bool(false),
+                    line: self.previous().line,
                 }))
             }
             TokenKind::True => {
                 self.advance();
                 Ok(Expr::Literal(Literal {
                     value: LiteralKind::Bool(true),
+                    line: self.previous().line,
                 }))
             }
             TokenKind::Nil => {
                 self.advance();
                 Ok(Expr::Literal(Literal {
                     value: LiteralKind::Nil,
+                    line: self.previous().line,
                 }))
             }
             TokenKind::String | TokenKind::Number => {
                 self.advance();
+                let token = self.previous();
                 Ok(Expr::Literal(Literal {
-                    value: self.previous().literal,
+                    value: token.literal,
+                    line: token.line,
                 }))
             }
+            TokenKind::Interpolation => {
+                self.advance();
+                let line = self.previous().line;
+                let mut parts = Vec::new();
+                loop {
+                    let text = self.previous();
+                    parts.push(Expr::Literal(Literal {
+                        value: text.literal,
+                        line: text.line,
+                    }));
+                    parts.push(self.expression()?);
+                    if self.token_match(&[TokenKind::Interpolation]) {
+                        continue;
+                    }
+
+                    let text =
+                        self.consume(TokenKind::String, "Expect '}' after interpolation.")?;
+                    parts.push(Expr::Literal(Literal {
+                        value: text.literal,
+                        line: text.line,
+                    }));
+                    break;
+                }
+                Ok(Expr::Interpolation(Interpolation { parts, line }))
+            }
             TokenKind::Super => {
                 self.advance();
                 let keyword = self.previous();
                 self.consume(TokenKind::Dot, "Expect '.' after 'super'.")?;
                 let method =
                     self.consume(TokenKind::Identifier, "Expect superclass method name.")?;
-                Ok(Expr::Super(Super { keyword, method }))
+                Ok(Expr::Super(Super {
+                    keyword,
+                    method,
+                    resolution: Cell::default(),
+                }))
             }
             TokenKind::This => {
                 self.advance();
                 Ok(Expr::This(This {
                     keyword: self.previous(),
+                    resolution: Cell::default(),
+                }))
+            }
+            TokenKind::Fun => {
+                self.advance();
+                let keyword = self.previous();
+                self.consume(TokenKind::LeftParenthesis, "Expect '(' after 'fun'.")?;
+                let (params, variadic, body) = self.function_body("lambda")?;
+                Ok(Expr::Lambda(Lambda {
+                    keyword,
+                    params,
+                    variadic,
+                    body,
                 }))
             }
+            TokenKind::Loop => {
+                self.advance();
+                self.loop_expression()
+            }
+            TokenKind::LeftBracket => {
+                self.advance();
+                let bracket = self.previous();
+                let mut elements = Vec::new();
+                if !self.check(&TokenKind::RightBracket) {
+                    loop {
+                        elements.push(self.expression()?);
+                        if !self.token_match(&[TokenKind::Comma]) {
+                            break;
+                        }
+                    }
+                }
+                self.consume(TokenKind::RightBracket, "Expect ']' after list elements.")?;
+                Ok(Expr::List(List { bracket, elements }))
+            }
+            TokenKind::LeftBrace => {
+                self.advance();
+                let brace = self.previous();
+                if self.starts_statement() {
+                    return self.block_expression(brace, None);
+                }
+
+                let mut entries = Vec::new();
+                if !self.check(&TokenKind::RightBrace) {
+                    // Only a ':' after the first expression tells a map from
+                    // a block that starts with an expression.
+                    let key = self.expression()?;
+                    if !self.check(&TokenKind::Colon) {
+                        return self.block_expression(brace, Some(key));
+                    }
+                    self.consume(TokenKind::Colon, "Expect ':' after map key.")?;
+                    entries.push((key, self.expression()?));
+                    while self.token_match(&[TokenKind::Comma]) {
+                        let key = self.expression()?;
+                        self.consume(TokenKind::Colon, "Expect ':' after map key.")?;
+                        let value = self.expression()?;
+                        entries.push((key, value));
+                    }
+                }
+                self.consume(TokenKind::RightBrace, "Expect '}' after map entries.")?;
+                Ok(Expr::Map(Map { brace, entries }))
+            }
             TokenKind::Identifier => {
                 self.advance();
                 Ok(Expr::Variable(Variable {
                     name: self.previous(),
+                    resolution: Cell::default(),
                 }))
             }
             TokenKind::LeftParenthesis => {
                 self.advance();
+                let line = self.previous().line;
                 let expr = self.expression()?;
                 self.consume(TokenKind::RightParenthesis, "Expect ')' after expression.")?;
                 Ok(Expr::Grouping(Grouping {
                     expr: Box::new(expr),
+                    line,
                 }))
             }
             _ => {
                 self.error(self.peek(), "Expect expression.");
-                self.advance();
                 Err(ParserError {})
             }
         }
     }
 
+    /// Parses the rest of a `{ ... }` in expression position once it is known
+    /// not to be a map. `leading` is an expression already parsed from the
+    /// front of it. A final expression without a ';' is the block's value.
+    fn block_expression(
+        &mut self,
+        brace: Token,
+        leading: Option<Expr>,
+    ) -> Result<Expr, ParserError> {
+        let mut statements = Vec::new();
+        let mut tail = leading;
+        loop {
+            if let Some(expression) = tail.take() {
+                if !self.token_match(&[TokenKind::Semicolon]) {
+                    self.consume(TokenKind::RightBrace, "Expect '}' after block.")?;
+                    return Ok(Expr::Block(BlockExpression {
+                        brace,
+                        statements,
+                        value: Some(Box::new(expression)),
+                    }));
+                }
+                statements.push(Stmt::Expression(Expression {
+                    expression: Box::new(expression),
+                    comments: Comments::default(),
+                }));
+            }
+
+            if self.check(&TokenKind::RightBrace) || self.is_at_end() {
+                self.consume(TokenKind::RightBrace, "Expect '}' after block.")?;
+                return Ok(Expr::Block(BlockExpression {
+                    brace,
+                    statements,
+                    value: None,
+                }));
+            }
+
+            match self.starts_statement() {
+                true => statements.push(self.declaration()?),
+                false => tail = Some(self.expression()?),
+            }
+        }
+    }
+
+    // Whether the next token can only begin a statement, never an expression.
+    fn starts_statement(&self) -> bool {
+        match self.peek().kind {
+            TokenKind::Fun => self.check_next(&TokenKind::Identifier),
+            TokenKind::Class
+            | TokenKind::Var
+            | TokenKind::Const
+            | TokenKind::Print
+            | TokenKind::If
+            | TokenKind::While
+            | TokenKind::For
+            | TokenKind::Return
+            | TokenKind::Break
+            | TokenKind::Continue
+            | TokenKind::Switch => true,
+            _ => false,
+        }
+    }
+
     fn token_match(&mut self, tokens: &[TokenKind]) -> bool {
         for token in tokens.iter() {
             if self.check(token) {
@@ -417,6 +1250,13 @@ impl Parser {
         self.peek().kind == *token
     }
 
+    fn check_next(&self, token: &TokenKind) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(next) => next.kind == *token,
+            None => false,
+        }
+    }
+
     fn advance(&mut self) {
         if !self.is_at_end() {
             self.current += 1;
@@ -449,6 +1289,51 @@ impl Parser {
         crate::error(token.clone(), message);
     }
 
+    fn leading_comments(&mut self) -> Vec<String> {
+        self.comments
+            .remove(&self.current)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|comment| comment.lexeme.to_string())
+            .collect()
+    }
+
+    fn attach_comments(&mut self, statement: &mut Stmt, leading: Vec<String>) {
+        if self.trivia {
+            let comments = statement.comments_mut();
+            comments.leading.extend(leading);
+            comments.trailing.extend(self.trailing_comments());
+        }
+    }
+
+    fn trailing_comments(&mut self) -> Vec<String> {
+        let line = self.previous().line;
+        let Some(comments) = self.comments.get_mut(&self.current) else {
+            return Vec::new();
+        };
+
+        let (trailing, leading): (Vec<Token>, Vec<Token>) =
+            comments.drain(..).partition(|comment| comment.line == line);
+        *comments = leading;
+        trailing
+            .into_iter()
+            .map(|comment| comment.lexeme.to_string())
+            .collect()
+    }
+
+    // An unmatched closer at statement start is almost always a copy-paste
+    // slip, so report it by name and drop only that token.
+    fn skip_stray_delimiter(&mut self) -> bool {
+        if !self.check(&TokenKind::RightBrace) && !self.check(&TokenKind::RightParenthesis) {
+            return false;
+        }
+
+        self.advance();
+        let token = self.previous();
+        crate::report(token.line, &format!("Unexpected '{}'.", token.lexeme));
+        true
+    }
+
     fn synchronize(&mut self) {
         self.advance();
 
@@ -461,11 +1346,13 @@ impl Parser {
                 TokenKind::Class
                 | TokenKind::Fun
                 | TokenKind::Var
+                | TokenKind::Const
                 | TokenKind::For
                 | TokenKind::If
                 | TokenKind::While
                 | TokenKind::Print
-                | TokenKind::Return => return,
+                | TokenKind::Return
+                | TokenKind::Switch => return,
                 _ => self.advance(),
             }
         }
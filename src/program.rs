@@ -0,0 +1,62 @@
+use crate::{
+    interpreter::{Interpreter, RuntimeError},
+    optimizer::fold_constants,
+    parser::{Parser, ParserError},
+    scanner::Scanner,
+    stmt::Stmt,
+};
+
+#[derive(Debug, Clone)]
+pub struct CompiledProgram {
+    statements: Vec<Stmt>,
+}
+
+impl CompiledProgram {
+    pub fn new(statements: Vec<Stmt>) -> Self {
+        CompiledProgram { statements }
+    }
+
+    pub fn compile(source: String) -> Result<Self, ParserError> {
+        let (tokens, errors) = Scanner::new(source).into_parts();
+        if !errors.is_empty() {
+            return Err(ParserError);
+        }
+
+        let statements = Parser::new(tokens).parse()?;
+        Ok(CompiledProgram::new(statements))
+    }
+
+    /// Folds constant expressions ahead of time, for programs that are run
+    /// more than once.
+    pub fn with_constant_folding(mut self) -> Self {
+        fold_constants(&mut self.statements);
+        self
+    }
+
+    pub fn statements(&self) -> &[Stmt] {
+        &self.statements
+    }
+
+    /// Runs the program without consuming it, so it can be run again.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::{
+    ///     interpreter::Interpreter, parser::Parser, program::CompiledProgram, scanner::Scanner,
+    /// };
+    ///
+    /// let source = "var count = 0;\nfor (var i = 0; i < 3; i = i + 1) count = count + i;";
+    /// let program = CompiledProgram::compile(source.to_string()).unwrap();
+    /// let tokens = Scanner::new("count".to_string()).scan_tokens().clone();
+    /// let count = Parser::new(tokens).parse_expression().unwrap();
+    /// let mut interpreter = Interpreter::new();
+    ///
+    /// program.run(&mut interpreter).unwrap();
+    /// assert_eq!(interpreter.interpret_expression(&count).unwrap(), "3");
+    /// interpreter.reset();
+    /// program.run(&mut interpreter).unwrap();
+    /// assert_eq!(interpreter.interpret_expression(&count).unwrap(), "3");
+    /// ```
+    pub fn run(&self, interpreter: &mut Interpreter) -> Result<(), RuntimeError> {
+        interpreter.interpret(&self.statements)
+    }
+}
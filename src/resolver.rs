@@ -0,0 +1,609 @@
+use std::{cell::Cell, collections::HashMap};
+
+use crate::{
+    diagnostic::Severity,
+    error,
+    expr::{self, Expr, ExpressionVisitor, Resolution},
+    stmt::{self, StatementVisitor, Stmt},
+    token::{Token, TokenKind},
+    warn,
+};
+
+#[derive(Debug, Clone, Copy)]
+struct Store {
+    line: usize,
+    region: usize,
+}
+
+#[derive(Debug)]
+struct Binding {
+    pending_store: Option<Store>,
+    // The declaration line of a local `var` that nothing has read yet.
+    unread: Option<usize>,
+    function_depth: usize,
+    captured: bool,
+    // Assigning to a constant fails when it runs, so no store to one is
+    // tracked.
+    constant: bool,
+}
+
+pub struct Resolver {
+    scopes: Vec<HashMap<String, Binding>>,
+    region: usize,
+    function_depth: usize,
+    in_class_method: bool,
+    local_redeclaration_errors: bool,
+    has_errors: bool,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Resolver {
+            scopes: vec![HashMap::new()],
+            region: 0,
+            function_depth: 0,
+            in_class_method: false,
+            local_redeclaration_errors: false,
+            has_errors: false,
+        }
+    }
+
+    /// Makes redeclaring a name inside the same local scope an error. Globals
+    /// may always be redeclared.
+    pub fn with_local_redeclaration_errors(mut self, enabled: bool) -> Self {
+        self.local_redeclaration_errors = enabled;
+        self
+    }
+
+    pub fn errors(&self) -> bool {
+        self.has_errors
+    }
+
+    pub fn resolve(&mut self, statements: &[Stmt]) {
+        for statement in statements.iter() {
+            self.resolve_stmt(statement);
+        }
+        self.unreachable(statements);
+    }
+
+    /// Warns about the first statement following one that always leaves the
+    /// block, since nothing after it can run.
+    fn unreachable(&mut self, statements: &[Stmt]) {
+        let after_exit = statements
+            .iter()
+            .position(always_exits)
+            .and_then(|index| statements.get(index + 1));
+        if let Some(statement) = after_exit {
+            self.warning(statement.line(), "Unreachable code.");
+        }
+    }
+
+    fn resolve_stmt(&mut self, stmt: &Stmt) {
+        stmt.accept(self)
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        expr.accept(self)
+    }
+
+    fn resolve_function(&mut self, params: &[Token], body: &[Stmt]) {
+        self.function_depth += 1;
+        self.enter_region();
+        self.begin_scope();
+        for param in params.iter() {
+            self.declare(param, None);
+        }
+        self.resolve(body);
+        self.end_scope();
+        self.exit_region();
+        self.function_depth -= 1;
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+
+        let mut unused: Vec<(usize, &str)> = scope
+            .iter()
+            .filter_map(|(name, binding)| binding.unread.map(|line| (line, name.as_str())))
+            .collect();
+        unused.sort();
+        for (line, name) in unused {
+            self.unused(line, name);
+        }
+
+        // An unused variable has been warned about already, whatever it holds.
+        let mut dead: Vec<(usize, String)> = scope
+            .into_iter()
+            .filter(|(_, binding)| !binding.captured && binding.unread.is_none())
+            .filter_map(|(name, binding)| binding.pending_store.map(|store| (store.line, name)))
+            .collect();
+        dead.sort();
+        for (line, name) in dead {
+            self.dead_store(line, &name);
+        }
+    }
+
+    /// Warns about a local variable that is never read. Globals are left
+    /// alone, since the REPL declares them to be used in later lines.
+    fn unused(&mut self, line: usize, name: &str) {
+        self.warning(line, &format!("Unused variable '{}'.", name));
+    }
+
+    fn enter_region(&mut self) {
+        self.region += 1;
+    }
+
+    fn exit_region(&mut self) {
+        self.region -= 1;
+        let region = self.region;
+        for scope in self.scopes.iter_mut() {
+            for binding in scope.values_mut() {
+                if binding
+                    .pending_store
+                    .is_some_and(|store| store.region > region)
+                {
+                    binding.pending_store = None;
+                }
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Token, store: Option<Store>) {
+        if self.local_redeclaration_errors
+            && self.scopes.len() > 1
+            && self
+                .scopes
+                .last()
+                .is_some_and(|scope| scope.contains_key(name.lexeme()))
+        {
+            self.has_errors = true;
+            error(
+                name.clone(),
+                "Already a variable with this name in this scope.",
+            );
+        }
+
+        let function_depth = self.function_depth;
+        let previous = self.scopes.last_mut().and_then(|scope| {
+            scope.insert(
+                name.lexeme.to_string(),
+                Binding {
+                    pending_store: store,
+                    unread: None,
+                    function_depth,
+                    captured: false,
+                    constant: false,
+                },
+            )
+        });
+
+        if let Some(Store { line, region }) = previous.and_then(|binding| binding.pending_store) {
+            if region == self.region {
+                self.dead_store(line, &name.lexeme);
+            }
+        }
+    }
+
+    // `this` and `super` live in scopes of their own that the interpreter
+    // creates when binding methods.
+    fn declare_keyword(&mut self, name: &str) {
+        let function_depth = self.function_depth;
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(
+                name.to_string(),
+                Binding {
+                    pending_store: None,
+                    unread: None,
+                    function_depth,
+                    captured: false,
+                    constant: false,
+                },
+            );
+        }
+    }
+
+    /// Records how many scopes out `name` was declared, or that it must be a
+    /// global when only the outermost scope (or none) declares it.
+    fn resolve_local(&self, name: &str, resolution: &Cell<Resolution>) {
+        let depth = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(name));
+        resolution.set(match depth {
+            Some(depth) if depth + 1 < self.scopes.len() => Resolution::Local(depth),
+            _ => Resolution::Global,
+        });
+    }
+
+    fn binding(&mut self, name: &Token) -> Option<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name.lexeme()))
+    }
+
+    fn read(&mut self, name: &Token) {
+        let function_depth = self.function_depth;
+        if let Some(binding) = self.binding(name) {
+            binding.pending_store = None;
+            binding.unread = None;
+            if binding.function_depth < function_depth {
+                binding.captured = true;
+            }
+        }
+    }
+
+    fn store(&mut self, name: &Token) {
+        let store = Store {
+            line: name.line,
+            region: self.region,
+        };
+        let function_depth = self.function_depth;
+        let Some(binding) = self.binding(name) else {
+            return;
+        };
+
+        if binding.function_depth < function_depth {
+            binding.captured = true;
+        }
+        if binding.constant {
+            return;
+        }
+        let previous = binding.pending_store.replace(store);
+        if let Some(Store { line, region }) = previous {
+            if region == store.region {
+                self.dead_store(line, &name.lexeme);
+            }
+        }
+    }
+
+    // Calls, getters, operator methods and `toString` run code that may read
+    // any global or any local a closure has captured, so no store to one of
+    // those before them is dead.
+    fn run_user_code(&mut self) {
+        for (depth, scope) in self.scopes.iter_mut().enumerate() {
+            for binding in scope.values_mut() {
+                if depth == 0 || binding.captured {
+                    binding.pending_store = None;
+                }
+            }
+        }
+    }
+
+    /// Warns about a value stored in a variable that is overwritten or goes
+    /// out of scope before anything reads it.
+    fn dead_store(&mut self, line: usize, name: &str) {
+        self.warning(
+            line,
+            &format!("Value assigned to '{}' is never read.", name),
+        );
+    }
+
+    // Warnings count against the program once they are denied.
+    fn warning(&mut self, line: usize, message: &str) {
+        if warn(line, message) == Severity::Error {
+            self.has_errors = true;
+        }
+    }
+}
+
+/// Whether running `stmt` always jumps out of the block it is in, by way of
+/// `return`, `break` or `continue`.
+fn always_exits(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Return(_) | Stmt::Break(_) | Stmt::Continue(_) => true,
+        Stmt::Block(block) => block.statements.iter().any(always_exits),
+        Stmt::If(stmt) => {
+            always_exits(&stmt.then_branch) && stmt.else_branch.as_deref().is_some_and(always_exits)
+        }
+        Stmt::Switch(stmt) => {
+            stmt.arms
+                .iter()
+                .all(|(_, body)| body.iter().any(always_exits))
+                && stmt
+                    .default
+                    .as_ref()
+                    .is_some_and(|body| body.iter().any(always_exits))
+        }
+        _ => false,
+    }
+}
+
+impl ExpressionVisitor<()> for Resolver {
+    fn visit_assignment(&mut self, expr: &expr::Assignment) {
+        self.resolve_expr(&expr.value);
+        self.resolve_local(expr.name.lexeme(), &expr.resolution);
+        self.store(&expr.name);
+    }
+
+    /// An operator on an instance may call one of its methods, such as
+    /// `add` for `+`.
+    fn visit_binary(&mut self, expr: &expr::Binary) {
+        self.resolve_expr(&expr.left);
+        self.resolve_expr(&expr.right);
+        if matches!(
+            expr.operator.kind,
+            TokenKind::Plus
+                | TokenKind::Minus
+                | TokenKind::Star
+                | TokenKind::Slash
+                | TokenKind::EqualEqual
+                | TokenKind::BangEqual
+        ) {
+            self.run_user_code();
+        }
+    }
+
+    fn visit_grouping(&mut self, expr: &expr::Grouping) {
+        self.resolve_expr(&expr.expr);
+    }
+
+    fn visit_literal(&self, _expr: &expr::Literal) {}
+
+    fn visit_logical(&mut self, expr: &expr::Logical) {
+        self.resolve_expr(&expr.left);
+        if expr.operator.kind == TokenKind::Xor {
+            self.resolve_expr(&expr.right);
+            return;
+        }
+
+        self.enter_region();
+        self.resolve_expr(&expr.right);
+        self.exit_region();
+    }
+
+    fn visit_unary(&mut self, expr: &expr::Unary) {
+        self.resolve_expr(&expr.right);
+    }
+
+    fn visit_postfix(&mut self, expr: &expr::Postfix) {
+        self.resolve_expr(&expr.target);
+        if let Expr::Variable(variable) = expr.target.as_ref() {
+            self.store(&variable.name);
+        }
+    }
+
+    fn visit_variable(&mut self, expr: &expr::Variable) {
+        self.resolve_local(expr.name.lexeme(), &expr.resolution);
+        self.read(&expr.name);
+    }
+
+    fn visit_call(&mut self, expr: &expr::Call) {
+        self.resolve_expr(&expr.callee);
+        for argument in expr.arguments.iter() {
+            self.resolve_expr(argument);
+        }
+
+        self.run_user_code();
+    }
+
+    fn visit_lambda(&mut self, expr: &expr::Lambda) {
+        self.resolve_function(&expr.params, &expr.body);
+    }
+
+    fn visit_list(&mut self, expr: &expr::List) {
+        for element in expr.elements.iter() {
+            self.resolve_expr(element);
+        }
+    }
+
+    fn visit_map(&mut self, expr: &expr::Map) {
+        for (key, value) in expr.entries.iter() {
+            self.resolve_expr(key);
+            self.resolve_expr(value);
+        }
+    }
+
+    fn visit_index(&mut self, expr: &expr::Index) {
+        self.resolve_expr(&expr.object);
+        self.resolve_expr(&expr.index);
+    }
+
+    fn visit_set_index(&mut self, expr: &expr::SetIndex) {
+        self.resolve_expr(&expr.object);
+        self.resolve_expr(&expr.index);
+        self.resolve_expr(&expr.value);
+    }
+
+    fn visit_get(&mut self, expr: &expr::Get) {
+        self.resolve_expr(&expr.object);
+        self.run_user_code();
+    }
+
+    fn visit_set(&mut self, expr: &expr::Set) {
+        self.resolve_expr(&expr.value);
+        self.resolve_expr(&expr.object);
+    }
+
+    fn visit_this(&mut self, expr: &expr::This) {
+        if self.in_class_method {
+            self.has_errors = true;
+            error(expr.keyword.clone(), "Can't use 'this' in a class method.");
+        }
+        self.resolve_local("this", &expr.resolution);
+    }
+
+    fn visit_super(&mut self, expr: &expr::Super) {
+        if self.in_class_method {
+            self.has_errors = true;
+            error(expr.keyword.clone(), "Can't use 'super' in a class method.");
+        }
+        self.resolve_local("super", &expr.resolution);
+    }
+
+    fn visit_interpolation(&mut self, expr: &expr::Interpolation) {
+        for part in expr.parts.iter() {
+            self.resolve_expr(part);
+        }
+        self.run_user_code();
+    }
+
+    fn visit_loop(&mut self, expr: &expr::Loop) {
+        self.enter_region();
+        self.begin_scope();
+        self.resolve(&expr.body);
+        self.end_scope();
+        self.exit_region();
+    }
+
+    fn visit_block_expression(&mut self, expr: &expr::BlockExpression) {
+        self.begin_scope();
+        self.resolve(&expr.statements);
+        if let Some(value) = &expr.value {
+            self.resolve_expr(value);
+        }
+        self.end_scope();
+    }
+}
+
+impl StatementVisitor<()> for Resolver {
+    fn visit_expression(&mut self, stmt: &stmt::Expression) {
+        self.resolve_expr(&stmt.expression);
+    }
+
+    fn visit_print(&mut self, stmt: &stmt::Print) {
+        for expression in stmt.expressions.iter() {
+            self.resolve_expr(expression);
+        }
+        self.run_user_code();
+    }
+
+    fn visit_var(&mut self, stmt: &stmt::Var) {
+        let store = stmt.initializer.as_ref().map(|initializer| {
+            self.resolve_expr(initializer);
+            Store {
+                line: stmt.name.line,
+                region: self.region,
+            }
+        });
+        self.declare(&stmt.name, store.filter(|_| !stmt.constant));
+        let local = self.scopes.len() > 1;
+        if let Some(binding) = self.binding(&stmt.name) {
+            binding.constant = stmt.constant;
+            if local {
+                binding.unread = Some(stmt.name.line);
+            }
+        }
+    }
+
+    fn visit_block(&mut self, stmt: &stmt::Block) {
+        self.begin_scope();
+        self.resolve(&stmt.statements);
+        self.end_scope();
+    }
+
+    fn visit_if(&mut self, stmt: &stmt::If) {
+        self.resolve_expr(&stmt.condition);
+        self.enter_region();
+        self.resolve_stmt(&stmt.then_branch);
+        self.exit_region();
+        if let Some(else_branch) = &stmt.else_branch {
+            self.enter_region();
+            self.resolve_stmt(else_branch);
+            self.exit_region();
+        }
+    }
+
+    fn visit_while(&mut self, stmt: &stmt::While) {
+        self.resolve_expr(&stmt.condition);
+        self.enter_region();
+        self.resolve_stmt(&stmt.body);
+        if let Some(increment) = &stmt.increment {
+            self.resolve_expr(increment);
+        }
+        self.exit_region();
+    }
+
+    fn visit_for_in(&mut self, stmt: &stmt::ForIn) {
+        self.resolve_expr(&stmt.iterable);
+        self.enter_region();
+        self.begin_scope();
+        self.declare(&stmt.variable, None);
+        self.resolve_stmt(&stmt.body);
+        self.end_scope();
+        self.exit_region();
+    }
+
+    fn visit_function(&mut self, stmt: &stmt::Function) {
+        self.declare(&stmt.name, None);
+        self.resolve_function(&stmt.params, &stmt.body);
+    }
+
+    fn visit_return(&mut self, stmt: &stmt::Return) {
+        if let Some(value) = &stmt.value {
+            self.resolve_expr(value);
+        }
+    }
+
+    fn visit_class(&mut self, stmt: &stmt::Class) {
+        self.declare(&stmt.name, None);
+        if let Some(super_class) = &stmt.super_class {
+            self.resolve_expr(super_class);
+            self.begin_scope();
+            self.declare_keyword("super");
+        }
+
+        let in_class_method = self.in_class_method;
+        for (methods, is_class_method) in [(&stmt.methods, false), (&stmt.class_methods, true)] {
+            self.in_class_method = is_class_method;
+            for method in methods.iter() {
+                let Stmt::Function(method) = method else {
+                    continue;
+                };
+                if is_class_method {
+                    self.resolve_function(&method.params, &method.body);
+                } else {
+                    self.begin_scope();
+                    self.declare_keyword("this");
+                    self.resolve_function(&method.params, &method.body);
+                    self.end_scope();
+                }
+            }
+        }
+        self.in_class_method = in_class_method;
+
+        if stmt.super_class.is_some() {
+            self.end_scope();
+        }
+    }
+
+    fn visit_switch(&mut self, stmt: &stmt::Switch) {
+        self.resolve_expr(&stmt.scrutinee);
+        let arms = stmt
+            .arms
+            .iter()
+            .map(|(patterns, body)| (patterns.as_slice(), body));
+        let default = stmt.default.iter().map(|body| (&[][..], body));
+        for (patterns, body) in arms.chain(default) {
+            for pattern in patterns.iter() {
+                self.resolve_expr(pattern);
+            }
+            self.enter_region();
+            self.begin_scope();
+            self.resolve(body);
+            self.end_scope();
+            self.exit_region();
+        }
+    }
+
+    fn visit_break(&mut self, stmt: &stmt::Break) {
+        if let Some(value) = &stmt.value {
+            self.resolve_expr(value);
+        }
+    }
+
+    fn visit_continue(&mut self, _stmt: &stmt::Continue) {}
+}
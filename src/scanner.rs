@@ -1,4 +1,94 @@
-use crate::token::{LiteralKind, Token, TokenKind, KEYWORDS};
+use std::ops::Range;
+
+use thiserror::Error;
+use unicode_ident::{is_xid_continue, is_xid_start};
+
+use crate::{
+    diagnostic::Diagnostic,
+    token::{keyword_kind, LiteralKind, Token, TokenKind},
+};
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ScanError {
+    #[error("Unexpected character: {character}")]
+    UnexpectedCharacter { character: char, line: usize },
+    #[error("Unterminated string.")]
+    UnterminatedString { line: usize },
+    #[error("{kind} literal too long.")]
+    LiteralTooLong { kind: &'static str, line: usize },
+    #[error("Invalid numeric separator.")]
+    InvalidNumericSeparator { line: usize },
+    #[error("Invalid {base} literal.")]
+    InvalidLiteral { base: &'static str, line: usize },
+    #[error("Invalid unicode escape.")]
+    InvalidUnicodeEscape { line: usize },
+    #[error("Unterminated interpolation.")]
+    UnterminatedInterpolation { line: usize },
+    #[error("Unterminated block comment.")]
+    UnterminatedComment { line: usize },
+}
+
+impl ScanError {
+    pub fn line(&self) -> usize {
+        match self {
+            ScanError::UnexpectedCharacter { line, .. }
+            | ScanError::UnterminatedString { line }
+            | ScanError::LiteralTooLong { line, .. }
+            | ScanError::InvalidNumericSeparator { line }
+            | ScanError::InvalidLiteral { line, .. }
+            | ScanError::InvalidUnicodeEscape { line }
+            | ScanError::UnterminatedInterpolation { line }
+            | ScanError::UnterminatedComment { line } => *line,
+        }
+    }
+}
+
+/// Whether `source` stops partway through a statement: inside a string or a
+/// block comment, or with brackets left open. The REPL keeps reading lines
+/// while this holds.
+///
+/// ```
+/// use codecrafters_interpreter::scanner::is_incomplete;
+///
+/// let mut input = String::from("if (true) {\n");
+/// assert!(is_incomplete(&input));
+/// input.push_str("  print 1; }\n");
+/// assert!(!is_incomplete(&input));
+///
+/// assert!(is_incomplete("print \"open"));
+/// assert!(is_incomplete("print 1; /* still"));
+/// assert!(!is_incomplete("print 1; /* done */"));
+/// assert!(!is_incomplete("print 1;"));
+/// ```
+pub fn is_incomplete(source: &str) -> bool {
+    let (tokens, errors) = Scanner::new(source.to_string())
+        .with_reporting(false)
+        .into_parts();
+    if errors.iter().any(|error| {
+        matches!(
+            error,
+            ScanError::UnterminatedString { .. }
+                | ScanError::UnterminatedInterpolation { .. }
+                | ScanError::UnterminatedComment { .. }
+        )
+    }) {
+        return true;
+    }
+
+    let mut depth: isize = 0;
+    for token in tokens.iter() {
+        match token.kind {
+            TokenKind::LeftParenthesis | TokenKind::LeftBrace | TokenKind::LeftBracket => {
+                depth += 1
+            }
+            TokenKind::RightParenthesis | TokenKind::RightBrace | TokenKind::RightBracket => {
+                depth -= 1
+            }
+            _ => {}
+        }
+    }
+    depth > 0
+}
 
 //lexer
 pub struct Scanner {
@@ -7,7 +97,61 @@ pub struct Scanner {
     start: usize,
     current: usize,
     line: usize,
-    has_errors: bool,
+    errors: Vec<ScanError>,
+    report: bool,
+    max_literal_len: Option<usize>,
+    comments: bool,
+    interpolations: Vec<OpenInterpolation>,
+    // The char range of each token in `tokens`.
+    spans: Vec<Range<usize>>,
+}
+
+/// A `${` whose closing `}` hasn't been scanned yet.
+#[derive(Debug)]
+struct OpenInterpolation {
+    braces: usize,
+    delimiter: usize,
+    line: usize,
+    // Where the string holding the `${` starts.
+    start: usize,
+}
+
+/// Decodes `\u{HEX}` escapes in a string literal's contents. Any other
+/// backslash is kept as written. Gives `None` for an escape that is malformed
+/// or names something that isn't a Unicode scalar value.
+fn unescape(contents: &[char]) -> Option<String> {
+    let mut literal = String::with_capacity(contents.len());
+    let mut i = 0;
+    while i < contents.len() {
+        if !contents[i..].starts_with(&['\\', 'u', '{']) {
+            literal.push(contents[i]);
+            i += 1;
+            continue;
+        }
+
+        let digits = &contents[i + 3..];
+        let end = digits.iter().position(|c| *c == '}')?;
+        if end == 0 || end > 6 {
+            return None;
+        }
+        let digits: String = digits[..end].iter().collect();
+        let code_point = u32::from_str_radix(&digits, 16).ok()?;
+        literal.push(char::from_u32(code_point)?);
+        i += 3 + end + 1;
+    }
+
+    Some(literal)
+}
+
+/// Whether a `_` in a number's digits has anything but a digit on either
+/// side of it.
+fn misplaced_separator(digits: &[char]) -> bool {
+    digits.iter().enumerate().any(|(i, c)| {
+        *c == '_'
+            && !(i > 0
+                && digits[i - 1].is_ascii_alphanumeric()
+                && digits.get(i + 1).is_some_and(|c| c.is_ascii_alphanumeric()))
+    })
 }
 
 impl Scanner {
@@ -18,23 +162,161 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
-            has_errors: false,
+            errors: Vec::new(),
+            report: true,
+            max_literal_len: None,
+            comments: false,
+            interpolations: Vec::new(),
+            spans: Vec::new(),
         }
     }
 
+    pub fn with_comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Whether lexical errors are printed to the error writer as they are
+    /// found. They are collected for `scan_errors` either way.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::{scanner::{ScanError, Scanner}, token::TokenKind};
+    ///
+    /// let mut scanner = Scanner::new("@#$ 1".to_string()).with_reporting(false);
+    /// let kind = scanner.scan_tokens()[0].kind();
+    /// let characters: Vec<char> = scanner
+    ///     .scan_errors()
+    ///     .iter()
+    ///     .map(|error| match error {
+    ///         ScanError::UnexpectedCharacter { character, line: 1 } => *character,
+    ///         error => panic!("unexpected error {error:?}"),
+    ///     })
+    ///     .collect();
+    /// assert_eq!(characters, vec!['@', '#', '$']);
+    /// assert_eq!(kind, TokenKind::Number);
+    /// ```
+    pub fn with_reporting(mut self, report: bool) -> Self {
+        self.report = report;
+        self
+    }
+
+    /// Rejects string literals whose contents, and number literals whose
+    /// text, run past `max_literal_len` characters. There is no limit by
+    /// default.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::scanner::Scanner;
+    ///
+    /// let scan = |source: &str| {
+    ///     let mut scanner = Scanner::new(source.to_string()).with_max_literal_len(5);
+    ///     let tokens = scanner.scan_tokens().len();
+    ///     (tokens, scanner.errors())
+    /// };
+    ///
+    /// assert_eq!(scan("\"hello\" 12345"), (3, false));
+    /// assert_eq!(scan("\"hello!\""), (1, true));
+    /// assert_eq!(scan("123.45"), (1, true));
+    /// assert_eq!(scan("0x123456"), (1, true));
+    /// ```
+    pub fn with_max_literal_len(mut self, max_literal_len: usize) -> Self {
+        self.max_literal_len = Some(max_literal_len);
+        self
+    }
+
+    /// Scans the whole source, ending the tokens with an EOF on the line the
+    /// source ends on.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::{scanner::Scanner, token::TokenKind};
+    ///
+    /// let mut scanner = Scanner::new("\n\n\n".to_string());
+    /// let tokens = scanner.scan_tokens();
+    /// assert_eq!(tokens.len(), 1);
+    /// assert_eq!(tokens[0].kind(), TokenKind::EOF);
+    /// assert_eq!(tokens[0].line(), 4);
+    ///
+    /// let mut scanner = Scanner::new("var a;\n\n\n".to_string());
+    /// assert_eq!(scanner.scan_tokens().last().unwrap().line(), 4);
+    /// ```
     pub fn scan_tokens(&mut self) -> &Vec<Token> {
+        self.scan();
+        &self.tokens
+    }
+
+    /// Scans the whole source without printing anything, handing back either
+    /// the tokens or every lexical error found along the way.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::scanner::{ScanError, Scanner};
+    ///
+    /// let errors = Scanner::new("var a = 1 @\nprint ~a;".to_string())
+    ///     .into_tokens()
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     errors,
+    ///     vec![
+    ///         ScanError::UnexpectedCharacter { character: '@', line: 1 },
+    ///         ScanError::UnexpectedCharacter { character: '~', line: 2 },
+    ///     ]
+    /// );
+    /// ```
+    pub fn into_tokens(mut self) -> Result<Vec<Token>, Vec<ScanError>> {
+        self.report = false;
+        self.scan();
+        match self.errors.is_empty() {
+            true => Ok(self.tokens),
+            false => Err(self.errors),
+        }
+    }
+
+    /// Scans the whole source and hands back the tokens together with any
+    /// errors, reporting them along the way unless reporting is off.
+    pub fn into_parts(mut self) -> (Vec<Token>, Vec<ScanError>) {
+        self.scan();
+        (self.tokens, self.errors)
+    }
+
+    /// Scans silently and pairs each token with the byte range it covers in
+    /// the source. Tokens that failed to scan are simply absent.
+    pub fn into_spanned_tokens(mut self) -> Vec<(Token, Range<usize>)> {
+        self.report = false;
+        self.scan();
+
+        let mut offsets: Vec<usize> = Vec::with_capacity(self.source.len() + 1);
+        let mut offset = 0;
+        for c in self.source.iter() {
+            offsets.push(offset);
+            offset += c.len_utf8();
+        }
+        offsets.push(offset);
+
+        self.tokens
+            .into_iter()
+            .zip(self.spans)
+            .map(|(token, span)| (token, offsets[span.start]..offsets[span.end]))
+            .collect()
+    }
+
+    fn scan(&mut self) {
         while !self.is_at_end() {
             self.start = self.current;
             self.scan_token();
         }
 
+        for open in std::mem::take(&mut self.interpolations) {
+            self.error_at(
+                ScanError::UnterminatedInterpolation { line: open.line },
+                open.start,
+            );
+        }
+
+        self.spans.push(self.current..self.current);
         self.tokens.push(Token::new(
             TokenKind::EOF,
             "".into(),
             LiteralKind::Nil,
             self.line,
         ));
-        &self.tokens
     }
 
     fn scan_token(&mut self) {
@@ -42,14 +324,54 @@ impl Scanner {
         match c {
             '(' => self.add_token(TokenKind::LeftParenthesis, LiteralKind::Nil),
             ')' => self.add_token(TokenKind::RightParenthesis, LiteralKind::Nil),
-            '{' => self.add_token(TokenKind::LeftBrace, LiteralKind::Nil),
-            '}' => self.add_token(TokenKind::RightBrace, LiteralKind::Nil),
+            '{' => {
+                if let Some(open) = self.interpolations.last_mut() {
+                    open.braces += 1;
+                }
+                self.add_token(TokenKind::LeftBrace, LiteralKind::Nil);
+            }
+            '}' if self
+                .interpolations
+                .last()
+                .is_some_and(|open| open.braces == 0) =>
+            {
+                if let Some(open) = self.interpolations.pop() {
+                    self.string_contents(open.delimiter, false, open.line);
+                }
+            }
+            '}' => {
+                if let Some(open) = self.interpolations.last_mut() {
+                    open.braces -= 1;
+                }
+                self.add_token(TokenKind::RightBrace, LiteralKind::Nil);
+            }
+            '[' => self.add_token(TokenKind::LeftBracket, LiteralKind::Nil),
+            ']' => self.add_token(TokenKind::RightBracket, LiteralKind::Nil),
             ',' => self.add_token(TokenKind::Comma, LiteralKind::Nil),
+            ':' => self.add_token(TokenKind::Colon, LiteralKind::Nil),
+            '.' if self.peek() == '.' && self.peek_next() == '.' => {
+                self.advance();
+                self.advance();
+                self.add_token(TokenKind::DotDotDot, LiteralKind::Nil);
+            }
             '.' => self.add_token(TokenKind::Dot, LiteralKind::Nil),
-            '-' => self.add_token(TokenKind::Minus, LiteralKind::Nil),
-            '+' => self.add_token(TokenKind::Plus, LiteralKind::Nil),
+            '-' => {
+                let kind = match self.is_next_expected('-') {
+                    true => TokenKind::MinusMinus,
+                    false => TokenKind::Minus,
+                };
+                self.add_token(kind, LiteralKind::Nil);
+            }
+            '+' => {
+                let kind = match self.is_next_expected('+') {
+                    true => TokenKind::PlusPlus,
+                    false => TokenKind::Plus,
+                };
+                self.add_token(kind, LiteralKind::Nil);
+            }
             ';' => self.add_token(TokenKind::Semicolon, LiteralKind::Nil),
             '*' => self.add_token(TokenKind::Star, LiteralKind::Nil),
+            '?' => self.add_token(TokenKind::Question, LiteralKind::Nil),
             '!' => {
                 let kind = match self.is_next_expected('=') {
                     true => TokenKind::BangEqual,
@@ -84,69 +406,254 @@ impl Scanner {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+
+                    if self.comments {
+                        let text: String =
+                            self.source[self.start + 2..self.current].iter().collect();
+                        self.add_token(TokenKind::Comment, LiteralKind::String(text.trim().into()));
+                    }
                 }
+                false if self.is_next_expected('*') => self.block_comment(),
                 false => self.add_token(TokenKind::Slash, LiteralKind::Nil),
             },
             ' ' | '\r' | '\t' => {}
             '\n' => self.line += 1,
-            '"' => {
-                while self.peek() != '"' && !self.is_at_end() {
-                    if self.peek() == '\n' {
-                        self.line += 1;
-                    }
+            '"' => self.string(false),
+            // `r"..."` keeps backslashes as written.
+            'r' if self.peek() == '"' => {
+                self.advance();
+                self.string(true);
+            }
+            '0' if matches!(self.peek(), 'x' | 'X' | 'b' | 'B') => {
+                let radix = match self.advance() {
+                    'x' | 'X' => 16,
+                    _ => 2,
+                };
+                self.radix_number(radix);
+            }
+            c if c.is_ascii_digit() => {
+                self.digits();
+                if self.peek() == '.'
+                    && (self.peek_next().is_ascii_digit() || self.peek_next() == '_')
+                {
                     self.advance();
+                    self.digits();
                 }
 
-                if self.is_at_end() {
-                    self.has_errors = true;
-                    eprintln!("[line {}] Error: Unterminated string.", self.line);
+                if self.is_literal_too_long(self.current - self.start) {
+                    self.error(ScanError::LiteralTooLong {
+                        kind: "Number",
+                        line: self.line,
+                    });
                     return;
                 }
 
-                self.advance();
-                let literal: String = self.source[self.start + 1..self.current - 1]
-                    .iter()
-                    .collect();
-                self.add_token(TokenKind::String, LiteralKind::String(literal));
-            }
-            c if c.is_digit(10) => {
-                while self.peek().is_digit(10) {
-                    self.advance();
-                }
-
-                if self.peek() == '.' && self.peek_next().is_digit(10) {
-                    self.advance();
-                    while self.peek().is_digit(10) {
-                        self.advance();
-                    }
+                let lexeme = &self.source[self.start..self.current];
+                if misplaced_separator(lexeme) {
+                    self.error(ScanError::InvalidNumericSeparator { line: self.line });
+                    return;
                 }
 
-                let literal: f64 = self.source[self.start..self.current]
+                let literal: f64 = lexeme
                     .iter()
+                    .filter(|c| **c != '_')
                     .collect::<String>()
                     .parse()
                     .unwrap();
 
                 self.add_token(TokenKind::Number, LiteralKind::Number(literal));
             }
-            c if c.is_alphabetic() || c == '_' => {
-                while self.peek().is_alphanumeric() || self.peek() == '_' {
+            // Identifiers follow Unicode's XID rules, so `café` and `π` are
+            // names while emoji and lone combining marks are rejected.
+            c if is_xid_start(c) || c == '_' => {
+                while is_xid_continue(self.peek()) {
                     self.advance();
                 }
 
                 let lexume: String = self.source[self.start..self.current].iter().collect();
-                match KEYWORDS.get(&lexume.as_str()) {
-                    Some(kind) => self.add_token(*kind, LiteralKind::Nil),
+                match keyword_kind(&lexume) {
+                    Some(kind) => self.add_token(kind, LiteralKind::Nil),
                     None => self.add_token(TokenKind::Identifier, LiteralKind::Nil),
                 }
             }
             _ => {
-                eprintln!("[line {}] Error: Unexpected character: {}", self.line, c);
-                self.has_errors = true;
+                self.error(ScanError::UnexpectedCharacter {
+                    character: c,
+                    line: self.line,
+                });
+            }
+        }
+    }
+
+    // Block comments don't nest: the first `*/` closes the comment.
+    fn block_comment(&mut self) {
+        let start_line = self.line;
+        while !(self.peek() == '*' && self.peek_next() == '/') {
+            if self.is_at_end() {
+                self.error(ScanError::UnterminatedComment { line: start_line });
+                return;
+            }
+            if self.peek() == '\n' {
+                self.line += 1;
+            }
+            self.advance();
+        }
+
+        self.advance();
+        self.advance();
+        if self.comments {
+            let text: String = self.source[self.start + 2..self.current - 2]
+                .iter()
+                .collect();
+            self.add_token_at(
+                TokenKind::Comment,
+                LiteralKind::String(text.trim().into()),
+                start_line,
+            );
+        }
+    }
+
+    // A string opened with `"""` runs until the next `"""`, so it may hold
+    // lone quotes. A newline right after the opening quotes is dropped;
+    // everything else, including indentation, is kept as written.
+    fn string(&mut self, raw: bool) {
+        let start_line = self.line;
+        let triple = self.peek() == '"' && self.peek_next() == '"';
+        let delimiter = match triple {
+            true => 3,
+            false => 1,
+        };
+        if triple {
+            self.advance();
+            self.advance();
+            if self.peek() == '\n' {
+                self.line += 1;
+                self.advance();
+            }
+        }
+
+        self.string_contents(delimiter, raw, start_line);
+    }
+
+    // Scans up to the closing quotes, or up to a `${`, which ends this piece
+    // of the string as an interpolation token. The scanner picks the string
+    // back up when it reaches the interpolation's closing `}`.
+    fn string_contents(&mut self, delimiter: usize, raw: bool, start_line: usize) {
+        let open = self.current;
+        let mut kind = TokenKind::String;
+        loop {
+            if self.is_at_end() {
+                self.error(ScanError::UnterminatedString { line: start_line });
+                return;
+            }
+            if self.at_closing_quote(delimiter) {
+                break;
+            }
+            if !raw && self.peek() == '$' && self.peek_next() == '{' {
+                kind = TokenKind::Interpolation;
+                break;
+            }
+
+            if self.peek() == '\n' {
+                self.line += 1;
+            }
+            self.advance();
+        }
+
+        let close = self.current;
+        self.current += match kind {
+            TokenKind::Interpolation => 2,
+            _ => delimiter,
+        };
+        let contents = &self.source[open..close];
+        if self.is_literal_too_long(contents.len()) {
+            self.error(ScanError::LiteralTooLong {
+                kind: "String",
+                line: start_line,
+            });
+            return;
+        }
+
+        let literal = match raw {
+            true => Some(contents.iter().collect()),
+            false => unescape(contents),
+        };
+        match literal {
+            Some(literal) => self.add_token(kind, LiteralKind::String(literal)),
+            None => self.error(ScanError::InvalidUnicodeEscape { line: start_line }),
+        }
+        if kind == TokenKind::Interpolation {
+            self.interpolations.push(OpenInterpolation {
+                braces: 0,
+                delimiter,
+                line: start_line,
+                start: self.start,
+            });
+        }
+    }
+
+    fn at_closing_quote(&self, delimiter: usize) -> bool {
+        self.source[self.current..]
+            .iter()
+            .take(delimiter)
+            .filter(|c| **c == '"')
+            .count()
+            == delimiter
+    }
+
+    /// Scans the digits after a `0x` or `0b` prefix. Like decimal numbers,
+    /// they may be split with `_` and are as long as the literal limit
+    /// allows, growing past `f64`'s exact integers rather than failing.
+    fn radix_number(&mut self, radix: u32) {
+        while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
+            self.advance();
+        }
+
+        if self.is_literal_too_long(self.current - self.start) {
+            self.error(ScanError::LiteralTooLong {
+                kind: "Number",
+                line: self.line,
+            });
+            return;
+        }
+
+        let digits = &self.source[self.start + 2..self.current];
+        if misplaced_separator(digits) {
+            self.error(ScanError::InvalidNumericSeparator { line: self.line });
+            return;
+        }
+
+        let digits: Option<Vec<u32>> = digits
+            .iter()
+            .filter(|c| **c != '_')
+            .map(|c| c.to_digit(radix))
+            .collect();
+        match digits {
+            Some(digits) if !digits.is_empty() => {
+                let value = digits
+                    .into_iter()
+                    .fold(0.0, |value, digit| value * radix as f64 + digit as f64);
+                self.add_token(TokenKind::Number, LiteralKind::Number(value));
+            }
+            _ => {
+                let base = match radix {
+                    16 => "hexadecimal",
+                    _ => "binary",
+                };
+                self.error(ScanError::InvalidLiteral {
+                    base,
+                    line: self.line,
+                });
             }
         }
     }
 
+    fn digits(&mut self) {
+        while self.peek().is_ascii_digit() || self.peek() == '_' {
+            self.advance();
+        }
+    }
+
     fn advance(&mut self) -> char {
         let c = self.source[self.current];
         self.current += 1;
@@ -154,9 +661,13 @@ impl Scanner {
     }
 
     fn add_token(&mut self, kind: TokenKind, literal: LiteralKind) {
+        self.add_token_at(kind, literal, self.line);
+    }
+
+    fn add_token_at(&mut self, kind: TokenKind, literal: LiteralKind, line: usize) {
         let lexeme: String = self.source[self.start..self.current].iter().collect();
-        self.tokens
-            .push(Token::new(kind, lexeme, literal, self.line));
+        self.spans.push(self.start..self.current);
+        self.tokens.push(Token::new(kind, lexeme, literal, line));
     }
 
     fn is_next_expected(&mut self, expected: char) -> bool {
@@ -186,11 +697,40 @@ impl Scanner {
         self.source[self.current + 1]
     }
 
+    fn is_literal_too_long(&self, len: usize) -> bool {
+        self.max_literal_len.is_some_and(|max| len > max)
+    }
+
     fn is_at_end(&self) -> bool {
-        return self.current >= self.source.len();
+        self.current >= self.source.len()
     }
 
     pub fn errors(&self) -> bool {
-        self.has_errors
+        !self.errors.is_empty()
+    }
+
+    pub fn scan_errors(&self) -> &[ScanError] {
+        &self.errors
+    }
+
+    fn error(&mut self, error: ScanError) {
+        self.error_at(error, self.start);
+    }
+
+    /// Records `error` against the char at `offset`, which gives the column
+    /// it is reported at.
+    fn error_at(&mut self, error: ScanError, offset: usize) {
+        if self.report {
+            let column = self.source[..offset]
+                .iter()
+                .rev()
+                .take_while(|c| **c != '\n')
+                .count()
+                + 1;
+            crate::diagnose(
+                Diagnostic::error(error.line(), &error.to_string()).with_column(column),
+            );
+        }
+        self.errors.push(error);
     }
 }
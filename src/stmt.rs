@@ -8,30 +8,54 @@ pub enum Stmt {
     Block(Block),
     If(If),
     While(While),
+    ForIn(ForIn),
     Function(Function),
     Return(Return),
     Class(Class),
+    Switch(Switch),
+    Break(Break),
+    Continue(Continue),
+}
+
+/// The comments kept with a statement when parsing with trivia: those on
+/// the lines above it, and those after it on its last line.
+#[derive(Debug, Clone, Default)]
+pub struct Comments {
+    pub leading: Vec<String>,
+    pub trailing: Vec<String>,
+}
+
+impl Comments {
+    pub fn is_empty(&self) -> bool {
+        self.leading.is_empty() && self.trailing.is_empty()
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Expression {
     pub expression: Box<Expr>,
+    pub comments: Comments,
 }
 
 #[derive(Debug, Clone)]
 pub struct Print {
-    pub expression: Box<Expr>,
+    pub expressions: Vec<Expr>,
+    pub comments: Comments,
 }
 
 #[derive(Debug, Clone)]
 pub struct Var {
     pub name: Token,
-    pub initializer: Box<Expr>,
+    pub initializer: Option<Box<Expr>>,
+    /// Declared with `const`, so the binding can never be assigned again.
+    pub constant: bool,
+    pub comments: Comments,
 }
 
 #[derive(Debug, Clone)]
 pub struct Block {
     pub statements: Vec<Stmt>,
+    pub comments: Comments,
 }
 
 #[derive(Debug, Clone)]
@@ -39,25 +63,41 @@ pub struct If {
     pub condition: Box<Expr>,
     pub then_branch: Box<Stmt>,
     pub else_branch: Option<Box<Stmt>>,
+    pub comments: Comments,
 }
 
 #[derive(Debug, Clone)]
 pub struct While {
     pub condition: Box<Expr>,
     pub body: Box<Stmt>,
+    pub increment: Option<Box<Expr>>,
+    pub comments: Comments,
+}
+
+#[derive(Debug, Clone)]
+pub struct ForIn {
+    pub variable: Token,
+    pub iterable: Box<Expr>,
+    pub body: Box<Stmt>,
+    pub comments: Comments,
 }
 
 #[derive(Debug, Clone)]
 pub struct Function {
     pub name: Token,
     pub params: Vec<Token>,
+    pub variadic: bool,
+    pub getter: bool,
     pub body: Vec<Stmt>,
+    pub comments: Comments,
 }
 
 #[derive(Debug, Clone)]
 pub struct Return {
     pub keyword: Token,
-    pub value: Box<Expr>,
+    /// `None` for a bare `return;`, which returns `nil`.
+    pub value: Option<Expr>,
+    pub comments: Comments,
 }
 
 #[derive(Debug, Clone)]
@@ -65,6 +105,92 @@ pub struct Class {
     pub name: Token,
     pub super_class: Option<Expr>,
     pub methods: Vec<Stmt>,
+    pub class_methods: Vec<Stmt>,
+    pub comments: Comments,
+}
+
+/// `switch (scrutinee) { case a, b: ... default: ... }`. The first arm with
+/// a pattern equal to the scrutinee runs, or else the default; control never
+/// falls through into the next arm, so a `break` in an arm belongs to the
+/// enclosing loop.
+#[derive(Debug, Clone)]
+pub struct Switch {
+    pub keyword: Token,
+    pub scrutinee: Expr,
+    pub arms: Vec<(Vec<Expr>, Vec<Stmt>)>,
+    pub default: Option<Vec<Stmt>>,
+    pub comments: Comments,
+}
+
+#[derive(Debug, Clone)]
+pub struct Break {
+    pub keyword: Token,
+    /// Only a `break` out of a `loop` expression may carry a value.
+    pub value: Option<Expr>,
+    pub comments: Comments,
+}
+
+#[derive(Debug, Clone)]
+pub struct Continue {
+    pub keyword: Token,
+    pub comments: Comments,
+}
+
+impl Stmt {
+    pub fn comments(&self) -> &Comments {
+        match self {
+            Stmt::Expression(stmt) => &stmt.comments,
+            Stmt::Print(stmt) => &stmt.comments,
+            Stmt::Var(stmt) => &stmt.comments,
+            Stmt::Block(stmt) => &stmt.comments,
+            Stmt::If(stmt) => &stmt.comments,
+            Stmt::While(stmt) => &stmt.comments,
+            Stmt::ForIn(stmt) => &stmt.comments,
+            Stmt::Function(stmt) => &stmt.comments,
+            Stmt::Return(stmt) => &stmt.comments,
+            Stmt::Class(stmt) => &stmt.comments,
+            Stmt::Switch(stmt) => &stmt.comments,
+            Stmt::Break(stmt) => &stmt.comments,
+            Stmt::Continue(stmt) => &stmt.comments,
+        }
+    }
+
+    /// The line a trace of this statement should point at.
+    pub fn line(&self) -> usize {
+        match self {
+            Stmt::Expression(stmt) => stmt.expression.line(),
+            Stmt::Print(stmt) => stmt.expressions.first().map_or(0, Expr::line),
+            Stmt::Var(stmt) => stmt.name.line,
+            Stmt::Block(stmt) => stmt.statements.first().map_or(0, Stmt::line),
+            Stmt::If(stmt) => stmt.condition.line(),
+            Stmt::While(stmt) => stmt.condition.line(),
+            Stmt::ForIn(stmt) => stmt.variable.line,
+            Stmt::Function(stmt) => stmt.name.line,
+            Stmt::Return(stmt) => stmt.keyword.line,
+            Stmt::Class(stmt) => stmt.name.line,
+            Stmt::Switch(stmt) => stmt.keyword.line,
+            Stmt::Break(stmt) => stmt.keyword.line,
+            Stmt::Continue(stmt) => stmt.keyword.line,
+        }
+    }
+
+    pub fn comments_mut(&mut self) -> &mut Comments {
+        match self {
+            Stmt::Expression(stmt) => &mut stmt.comments,
+            Stmt::Print(stmt) => &mut stmt.comments,
+            Stmt::Var(stmt) => &mut stmt.comments,
+            Stmt::Block(stmt) => &mut stmt.comments,
+            Stmt::If(stmt) => &mut stmt.comments,
+            Stmt::While(stmt) => &mut stmt.comments,
+            Stmt::ForIn(stmt) => &mut stmt.comments,
+            Stmt::Function(stmt) => &mut stmt.comments,
+            Stmt::Return(stmt) => &mut stmt.comments,
+            Stmt::Class(stmt) => &mut stmt.comments,
+            Stmt::Switch(stmt) => &mut stmt.comments,
+            Stmt::Break(stmt) => &mut stmt.comments,
+            Stmt::Continue(stmt) => &mut stmt.comments,
+        }
+    }
 }
 
 pub trait StatementVisitor<T> {
@@ -74,9 +200,13 @@ pub trait StatementVisitor<T> {
     fn visit_block(&mut self, stmt: &Block) -> T;
     fn visit_if(&mut self, stmt: &If) -> T;
     fn visit_while(&mut self, stmt: &While) -> T;
+    fn visit_for_in(&mut self, stmt: &ForIn) -> T;
     fn visit_function(&mut self, stmt: &Function) -> T;
     fn visit_return(&mut self, stmt: &Return) -> T;
     fn visit_class(&mut self, stmt: &Class) -> T;
+    fn visit_switch(&mut self, stmt: &Switch) -> T;
+    fn visit_break(&mut self, stmt: &Break) -> T;
+    fn visit_continue(&mut self, stmt: &Continue) -> T;
 }
 
 impl Stmt {
@@ -88,9 +218,13 @@ impl Stmt {
             Stmt::Block(block) => visitor.visit_block(block),
             Stmt::If(stmt) => visitor.visit_if(stmt),
             Stmt::While(stmt) => visitor.visit_while(stmt),
+            Stmt::ForIn(stmt) => visitor.visit_for_in(stmt),
             Stmt::Function(fun) => visitor.visit_function(fun),
             Stmt::Return(r) => visitor.visit_return(r),
             Stmt::Class(class) => visitor.visit_class(class),
+            Stmt::Switch(switch) => visitor.visit_switch(switch),
+            Stmt::Break(b) => visitor.visit_break(b),
+            Stmt::Continue(c) => visitor.visit_continue(c),
         }
     }
 }
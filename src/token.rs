@@ -1,6 +1,6 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{fmt::Display, rc::Rc};
 
-use lazy_static::lazy_static;
+use crate::interpreter::format_number;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TokenKind {
@@ -9,13 +9,17 @@ pub enum TokenKind {
     RightParenthesis,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
+    Colon,
     Dot,
     Minus,
     Plus,
     Semicolon,
     Slash,
     Star,
+    Question,
     // Or or more character tokens
     Bang,
     BangEqual,
@@ -25,27 +29,42 @@ pub enum TokenKind {
     GreaterEqual,
     Less,
     LessEqual,
+    DotDotDot,
+    MinusMinus,
+    PlusPlus,
     // Literals
     Identifier,
     String,
+    Interpolation,
     Number,
+    Comment,
     //Keywords
     And,
+    Break,
+    Case,
     Class,
+    Const,
+    Continue,
+    Default,
     Else,
     False,
     Fun,
     For,
     If,
+    In,
+    Is,
+    Loop,
     Nil,
     Or,
     Print,
     Return,
     Super,
+    Switch,
     This,
     True,
     Var,
     While,
+    Xor,
     //
     EOF,
 }
@@ -58,13 +77,17 @@ impl Display for TokenKind {
             RightParenthesis => write!(f, "RIGHT_PAREN"),
             LeftBrace => write!(f, "LEFT_BRACE"),
             RightBrace => write!(f, "RIGHT_BRACE"),
+            LeftBracket => write!(f, "LEFT_BRACKET"),
+            RightBracket => write!(f, "RIGHT_BRACKET"),
             Comma => write!(f, "COMMA"),
+            Colon => write!(f, "COLON"),
             Dot => write!(f, "DOT"),
             Minus => write!(f, "MINUS"),
             Plus => write!(f, "PLUS"),
             Semicolon => write!(f, "SEMICOLON"),
             Slash => write!(f, "SLASH"),
             Star => write!(f, "STAR"),
+            Question => write!(f, "QUESTION"),
             Bang => write!(f, "BANG"),
             BangEqual => write!(f, "BANG_EQUAL"),
             Equal => write!(f, "EQUAL"),
@@ -73,25 +96,40 @@ impl Display for TokenKind {
             GreaterEqual => write!(f, "GREATER_EQUAL"),
             Less => write!(f, "LESS"),
             LessEqual => write!(f, "LESS_EQUAL"),
+            DotDotDot => write!(f, "DOT_DOT_DOT"),
+            MinusMinus => write!(f, "MINUS_MINUS"),
+            PlusPlus => write!(f, "PLUS_PLUS"),
             Identifier => write!(f, "IDENTIFIER"),
             String => write!(f, "STRING"),
+            Interpolation => write!(f, "INTERPOLATION"),
             Number => write!(f, "NUMBER"),
+            Comment => write!(f, "COMMENT"),
             And => write!(f, "AND"),
+            Break => write!(f, "BREAK"),
+            Case => write!(f, "CASE"),
             Class => write!(f, "CLASS"),
+            Const => write!(f, "CONST"),
+            Continue => write!(f, "CONTINUE"),
+            Default => write!(f, "DEFAULT"),
             Else => write!(f, "ELSE"),
             False => write!(f, "FALSE"),
             Fun => write!(f, "FUN"),
             For => write!(f, "FOR"),
             If => write!(f, "IF"),
+            In => write!(f, "IN"),
+            Is => write!(f, "IS"),
+            Loop => write!(f, "LOOP"),
             Nil => write!(f, "NIL"),
             Or => write!(f, "OR"),
             Print => write!(f, "PRINT"),
             Return => write!(f, "RETURN"),
             Super => write!(f, "SUPER"),
+            Switch => write!(f, "SWITCH"),
             This => write!(f, "THIS"),
             True => write!(f, "TRUE"),
             Var => write!(f, "VAR"),
             While => write!(f, "WHILE"),
+            Xor => write!(f, "XOR"),
             EOF => write!(f, "EOF"),
         }
     }
@@ -105,80 +143,206 @@ pub enum LiteralKind {
     Nil,
 }
 
+/// Renders a literal the way `parse` prints it: numbers always keep a
+/// fraction (`2.0`, `2.5`), strings are verbatim and `nil` is `nil`. `print`
+/// drops the `.0` instead, so runtime values go through `stringify`.
+///
+/// ```
+/// use codecrafters_interpreter::token::LiteralKind;
+///
+/// assert_eq!(String::from(LiteralKind::Number(2.0)), "2.0");
+/// assert_eq!(String::from(LiteralKind::Number(2.5)), "2.5");
+/// assert_eq!(String::from(LiteralKind::String("hi".to_string())), "hi");
+/// assert_eq!(String::from(LiteralKind::Bool(true)), "true");
+/// assert_eq!(String::from(LiteralKind::Bool(false)), "false");
+/// assert_eq!(String::from(LiteralKind::Nil), "nil");
+/// ```
 impl From<LiteralKind> for String {
     fn from(literal: LiteralKind) -> Self {
         match literal {
             LiteralKind::String(string) => string,
             LiteralKind::Number(number) => {
-                let mut number = number.to_string();
-                if !number.contains(".") {
+                let mut number = format_number(number);
+                if !number.contains('.') {
                     number.push_str(".0");
-                } else {
-                    let mut split = number.split(".").collect::<Vec<&str>>();
-                    if split[1].chars().all(|c| c == '0') {
-                        split.pop();
-                        split.push("0");
-                        number = split.join(".");
-                    }
                 }
-                number.to_string()
+                number
             }
             LiteralKind::Bool(bool) => bool.to_string(),
-            LiteralKind::Nil => "null".to_string(),
+            LiteralKind::Nil => "nil".to_string(),
         }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Token {
-    pub kind: TokenKind,
-    pub lexeme: String,
-    pub literal: LiteralKind,
-    pub line: usize,
+    pub(crate) kind: TokenKind,
+    // Tokens are cloned freely by the parser and the AST, so the lexeme is
+    // shared rather than copied each time.
+    pub(crate) lexeme: Rc<str>,
+    pub(crate) literal: LiteralKind,
+    pub(crate) line: usize,
 }
 
 impl Token {
     pub fn new(kind: TokenKind, lexeme: String, literal: LiteralKind, line: usize) -> Self {
         Token {
             kind,
-            lexeme,
+            lexeme: lexeme.into(),
             literal,
             line,
         }
     }
+
+    /// ```
+    /// use codecrafters_interpreter::{scanner::Scanner, token::TokenKind};
+    ///
+    /// let mut scanner = Scanner::new("var a = 1;\nprint a;".to_string());
+    /// let tokens = scanner.scan_tokens();
+    /// let prints: Vec<usize> = tokens
+    ///     .iter()
+    ///     .filter(|token| token.kind() == TokenKind::Print)
+    ///     .map(|token| token.line())
+    ///     .collect();
+    /// assert_eq!(prints, vec![2]);
+    /// ```
+    pub fn kind(&self) -> TokenKind {
+        self.kind
+    }
+
+    pub fn lexeme(&self) -> &str {
+        &self.lexeme
+    }
+
+    pub fn literal(&self) -> &LiteralKind {
+        &self.literal
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Serializes the token as a JSON object. Number and string literals keep
+    /// their JSON types and every other token's literal is `null`.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::scanner::Scanner;
+    ///
+    /// let mut scanner = Scanner::new("12.5 \"hi\"".to_string());
+    /// let json: Vec<String> = scanner.scan_tokens().iter().map(|t| t.to_json()).collect();
+    /// assert_eq!(
+    ///     json,
+    ///     vec![
+    ///         r#"{"kind": "NUMBER", "lexeme": "12.5", "literal": 12.5, "line": 1}"#,
+    ///         r#"{"kind": "STRING", "lexeme": "\"hi\"", "literal": "hi", "line": 1}"#,
+    ///         r#"{"kind": "EOF", "lexeme": "", "literal": null, "line": 1}"#,
+    ///     ]
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let literal = match &self.literal {
+            LiteralKind::Number(number) => number.to_string(),
+            LiteralKind::String(string) => json_string(string),
+            _ => "null".to_string(),
+        };
+        format!(
+            "{{\"kind\": {}, \"lexeme\": {}, \"literal\": {}, \"line\": {}}}",
+            json_string(&self.kind.to_string()),
+            json_string(&self.lexeme),
+            literal,
+            self.line
+        )
+    }
+}
+
+fn json_string(string: &str) -> String {
+    let mut json = String::with_capacity(string.len() + 2);
+    json.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
 }
 
+/// The `tokenize` format: kind, lexeme and literal, with `null` standing in
+/// for tokens that carry no literal.
+///
+/// ```
+/// use codecrafters_interpreter::scanner::Scanner;
+///
+/// let mut scanner = Scanner::new("true nil".to_string());
+/// let tokens: Vec<String> = scanner.scan_tokens().iter().map(|t| t.to_string()).collect();
+/// assert_eq!(tokens, vec!["TRUE true null", "NIL nil null", "EOF  null"]);
+/// ```
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} {} {}",
-            self.kind,
-            self.lexeme,
-            String::from(self.literal.clone())
-        )
+        let literal = match &self.literal {
+            LiteralKind::Nil => "null".to_string(),
+            literal => String::from(literal.clone()),
+        };
+        write!(f, "{} {} {}", self.kind, self.lexeme, literal)
     }
 }
 
-lazy_static! {
-    pub static ref KEYWORDS: HashMap<&'static str, TokenKind> = {
-        let mut keywords = HashMap::new();
-        keywords.insert("and", TokenKind::And);
-        keywords.insert("class", TokenKind::Class);
-        keywords.insert("else", TokenKind::Else);
-        keywords.insert("false", TokenKind::False);
-        keywords.insert("for", TokenKind::For);
-        keywords.insert("fun", TokenKind::Fun);
-        keywords.insert("if", TokenKind::If);
-        keywords.insert("nil", TokenKind::Nil);
-        keywords.insert("or", TokenKind::Or);
-        keywords.insert("print", TokenKind::Print);
-        keywords.insert("return", TokenKind::Return);
-        keywords.insert("super", TokenKind::Super);
-        keywords.insert("this", TokenKind::This);
-        keywords.insert("true", TokenKind::True);
-        keywords.insert("var", TokenKind::Var);
-        keywords.insert("while", TokenKind::While);
-        keywords
-    };
+/// The kind of reserved word `identifier` spells, if any.
+///
+/// ```
+/// use codecrafters_interpreter::{
+///     scanner::Scanner,
+///     token::{keyword_kind, TokenKind},
+/// };
+///
+/// for keyword in [
+///     "and", "break", "class", "continue", "else", "false", "for", "fun", "if", "in", "nil",
+///     "or", "print", "return", "super", "this", "true", "var", "while", "xor",
+/// ] {
+///     let kind = keyword_kind(keyword).unwrap();
+///     assert_eq!(kind.to_string(), keyword.to_uppercase());
+///     let mut scanner = Scanner::new(keyword.to_string());
+///     assert_eq!(scanner.scan_tokens()[0].kind(), kind);
+/// }
+///
+/// assert_eq!(keyword_kind("print_"), None);
+/// let mut scanner = Scanner::new("print_".to_string());
+/// assert_eq!(scanner.scan_tokens()[0].kind(), TokenKind::Identifier);
+/// ```
+pub fn keyword_kind(identifier: &str) -> Option<TokenKind> {
+    match identifier {
+        "and" => Some(TokenKind::And),
+        "break" => Some(TokenKind::Break),
+        "case" => Some(TokenKind::Case),
+        "class" => Some(TokenKind::Class),
+        "const" => Some(TokenKind::Const),
+        "continue" => Some(TokenKind::Continue),
+        "default" => Some(TokenKind::Default),
+        "else" => Some(TokenKind::Else),
+        "false" => Some(TokenKind::False),
+        "for" => Some(TokenKind::For),
+        "fun" => Some(TokenKind::Fun),
+        "if" => Some(TokenKind::If),
+        "in" => Some(TokenKind::In),
+        "is" => Some(TokenKind::Is),
+        "loop" => Some(TokenKind::Loop),
+        "nil" => Some(TokenKind::Nil),
+        "or" => Some(TokenKind::Or),
+        "print" => Some(TokenKind::Print),
+        "return" => Some(TokenKind::Return),
+        "super" => Some(TokenKind::Super),
+        "switch" => Some(TokenKind::Switch),
+        "this" => Some(TokenKind::This),
+        "true" => Some(TokenKind::True),
+        "var" => Some(TokenKind::Var),
+        "while" => Some(TokenKind::While),
+        "xor" => Some(TokenKind::Xor),
+        _ => None,
+    }
 }
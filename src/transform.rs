@@ -0,0 +1,164 @@
+use crate::{expr::Expr, stmt::Stmt};
+
+/// Calls `f` on every expression in `stmts`, parents before their children,
+/// so a transform can rewrite the tree in place.
+///
+/// ```
+/// use codecrafters_interpreter::{
+///     expr::Expr,
+///     interpreter::Interpreter,
+///     parser::Parser,
+///     scanner::Scanner,
+///     token::{LiteralKind, Token, TokenKind},
+///     transform::walk_mut,
+/// };
+///
+/// let source = "var new = 2;\nif (true) print old + 10;";
+/// let tokens = Scanner::new(source.to_string()).scan_tokens().clone();
+/// let mut statements = Parser::new(tokens).parse().unwrap();
+/// assert!(Interpreter::new().interpret(&statements).is_err());
+///
+/// walk_mut(&mut statements, &mut |expr| {
+///     if let Expr::Variable(variable) = expr {
+///         if variable.name.lexeme() == "old" {
+///             let line = variable.name.line();
+///             variable.name = Token::new(TokenKind::Identifier, "new".into(), LiteralKind::Nil, line);
+///         }
+///     }
+/// });
+/// assert!(Interpreter::new().interpret(&statements).is_ok());
+/// ```
+pub fn walk_mut(stmts: &mut [Stmt], f: &mut impl FnMut(&mut Expr)) {
+    for stmt in stmts.iter_mut() {
+        walk_stmt_mut(stmt, f);
+    }
+}
+
+pub fn walk_stmt_mut(stmt: &mut Stmt, f: &mut impl FnMut(&mut Expr)) {
+    match stmt {
+        Stmt::Expression(stmt) => walk_expr_mut(&mut stmt.expression, f),
+        Stmt::Print(stmt) => {
+            for expression in stmt.expressions.iter_mut() {
+                walk_expr_mut(expression, f);
+            }
+        }
+        Stmt::Var(stmt) => {
+            if let Some(initializer) = &mut stmt.initializer {
+                walk_expr_mut(initializer, f);
+            }
+        }
+        Stmt::Block(stmt) => walk_mut(&mut stmt.statements, f),
+        Stmt::If(stmt) => {
+            walk_expr_mut(&mut stmt.condition, f);
+            walk_stmt_mut(&mut stmt.then_branch, f);
+            if let Some(else_branch) = &mut stmt.else_branch {
+                walk_stmt_mut(else_branch, f);
+            }
+        }
+        Stmt::While(stmt) => {
+            walk_expr_mut(&mut stmt.condition, f);
+            walk_stmt_mut(&mut stmt.body, f);
+            if let Some(increment) = &mut stmt.increment {
+                walk_expr_mut(increment, f);
+            }
+        }
+        Stmt::ForIn(stmt) => {
+            walk_expr_mut(&mut stmt.iterable, f);
+            walk_stmt_mut(&mut stmt.body, f);
+        }
+        Stmt::Function(stmt) => walk_mut(&mut stmt.body, f),
+        Stmt::Return(stmt) => {
+            if let Some(value) = &mut stmt.value {
+                walk_expr_mut(value, f);
+            }
+        }
+        Stmt::Class(stmt) => {
+            if let Some(super_class) = &mut stmt.super_class {
+                walk_expr_mut(super_class, f);
+            }
+            walk_mut(&mut stmt.methods, f);
+            walk_mut(&mut stmt.class_methods, f);
+        }
+        Stmt::Switch(stmt) => {
+            walk_expr_mut(&mut stmt.scrutinee, f);
+            for (patterns, body) in stmt.arms.iter_mut() {
+                for pattern in patterns.iter_mut() {
+                    walk_expr_mut(pattern, f);
+                }
+                walk_mut(body, f);
+            }
+            if let Some(default) = &mut stmt.default {
+                walk_mut(default, f);
+            }
+        }
+        Stmt::Break(stmt) => {
+            if let Some(value) = &mut stmt.value {
+                walk_expr_mut(value, f);
+            }
+        }
+        Stmt::Continue(_) => {}
+    }
+}
+
+pub fn walk_expr_mut(expr: &mut Expr, f: &mut impl FnMut(&mut Expr)) {
+    f(expr);
+    match expr {
+        Expr::Assignment(expr) => walk_expr_mut(&mut expr.value, f),
+        Expr::Binary(expr) => {
+            walk_expr_mut(&mut expr.left, f);
+            walk_expr_mut(&mut expr.right, f);
+        }
+        Expr::Grouping(expr) => walk_expr_mut(&mut expr.expr, f),
+        Expr::Logical(expr) => {
+            walk_expr_mut(&mut expr.left, f);
+            walk_expr_mut(&mut expr.right, f);
+        }
+        Expr::Unary(expr) => walk_expr_mut(&mut expr.right, f),
+        Expr::Postfix(expr) => walk_expr_mut(&mut expr.target, f),
+        Expr::Call(expr) => {
+            walk_expr_mut(&mut expr.callee, f);
+            for argument in expr.arguments.iter_mut() {
+                walk_expr_mut(argument, f);
+            }
+        }
+        Expr::Lambda(expr) => walk_mut(&mut expr.body, f),
+        Expr::List(expr) => {
+            for element in expr.elements.iter_mut() {
+                walk_expr_mut(element, f);
+            }
+        }
+        Expr::Map(expr) => {
+            for (key, value) in expr.entries.iter_mut() {
+                walk_expr_mut(key, f);
+                walk_expr_mut(value, f);
+            }
+        }
+        Expr::Index(expr) => {
+            walk_expr_mut(&mut expr.object, f);
+            walk_expr_mut(&mut expr.index, f);
+        }
+        Expr::SetIndex(expr) => {
+            walk_expr_mut(&mut expr.object, f);
+            walk_expr_mut(&mut expr.index, f);
+            walk_expr_mut(&mut expr.value, f);
+        }
+        Expr::Get(expr) => walk_expr_mut(&mut expr.object, f),
+        Expr::Set(expr) => {
+            walk_expr_mut(&mut expr.object, f);
+            walk_expr_mut(&mut expr.value, f);
+        }
+        Expr::Interpolation(expr) => {
+            for part in expr.parts.iter_mut() {
+                walk_expr_mut(part, f);
+            }
+        }
+        Expr::Loop(expr) => walk_mut(&mut expr.body, f),
+        Expr::Block(expr) => {
+            walk_mut(&mut expr.statements, f);
+            if let Some(value) = &mut expr.value {
+                walk_expr_mut(value, f);
+            }
+        }
+        Expr::Literal(_) | Expr::Variable(_) | Expr::This(_) | Expr::Super(_) => {}
+    }
+}
@@ -0,0 +1,68 @@
+//! Runs Lox sources through the interpreter binary, the way a user would.
+
+// Each test file pulls in the helpers it needs and leaves the rest unused.
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static SOURCES: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs `command` on `source`, handing back what was printed to stdout and
+/// the diagnostics printed to stderr.
+pub fn lox(command: &str, source: &str) -> (String, String) {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!(
+        "{}-{}.lox",
+        std::process::id(),
+        SOURCES.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg(command)
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+pub fn run(source: &str) -> (String, String) {
+    lox("run", source)
+}
+
+pub fn evaluate(source: &str) -> (String, String) {
+    lox("evaluate", source)
+}
+
+/// Types `input` into the REPL, handing back everything it printed, prompts
+/// included, and the diagnostics printed to stderr.
+pub fn repl(input: &str) -> (String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg("repl")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
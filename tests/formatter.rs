@@ -0,0 +1,56 @@
+mod common;
+
+use common::lox;
+
+#[test]
+fn comments_on_class_members_and_bare_returns_survive_formatting() {
+    let source = "\
+// a shape
+class A {
+    // makes one
+    init() {} // ctor
+    // area
+    area {
+        return 1;
+    }
+    class make() {
+        return nil;
+    } // factory
+}
+fun f() {
+    return;
+}
+";
+    assert_eq!(lox("fmt", source), (source.into(), "".into()));
+}
+
+#[test]
+fn class_members_keep_their_order_and_comments_keep_their_line() {
+    let source = "\
+class A {
+    init() {
+        this.x = 1; // set x
+    }
+    class make() {
+        return A();
+    }
+}
+// leading
+print A.make().x; /* trailing */
+";
+    assert_eq!(lox("fmt", source), (source.into(), "".into()));
+}
+
+#[test]
+fn an_else_if_continues_on_the_else_line() {
+    let formatted =
+        "if (a) {\n    print 1;\n} else if (b) {\n    print 2;\n} else {\n    print 3;\n}\n";
+    assert_eq!(
+        lox(
+            "fmt",
+            "if (a) { print 1; } else if (b) { print 2; } else { print 3; }"
+        ),
+        (formatted.into(), "".into())
+    );
+    assert_eq!(lox("fmt", formatted), (formatted.into(), "".into()));
+}
@@ -0,0 +1,691 @@
+mod common;
+
+use common::{evaluate, lox, run};
+
+#[test]
+fn strings_compare_by_their_characters() {
+    assert_eq!(evaluate(r#""apple" < "banana""#).0, "true\n");
+    assert_eq!(evaluate(r#""b" >= "b""#).0, "true\n");
+    assert_eq!(
+        evaluate(r#""b" < 1"#).1,
+        "[line 1] Error: Operands must be numbers, got \"b\" and 1.\n"
+    );
+}
+
+#[test]
+fn operand_errors_name_the_offending_values() {
+    assert_eq!(
+        evaluate(r#""foo" - 3"#).1,
+        "[line 1] Error: Operands must be numbers, got \"foo\" and 3.\n"
+    );
+    assert_eq!(
+        evaluate("nil * true").1,
+        "[line 1] Error: Operands must be numbers, got nil and true.\n"
+    );
+}
+
+#[test]
+fn division_by_zero_reports_the_operator_line() {
+    assert_eq!(
+        run("var a = 1;\nprint a\n  / 0;"),
+        ("".into(), "[line 3] Error: Division by zero.\n".into())
+    );
+}
+
+#[test]
+fn continue_leaves_no_variables_behind_between_iterations() {
+    let source = r#"
+        var i = 0;
+        while (i < 4) {
+            i = i + 1;
+            var fresh = "fresh";
+            print fresh;
+            fresh = "used";
+            if (i == 2) continue;
+            print i;
+        }
+        print fresh;
+    "#;
+    assert_eq!(
+        run(source),
+        (
+            "fresh\n1\nfresh\nfresh\n3\nfresh\n4\n".into(),
+            "[line 7] Warning: Value assigned to 'fresh' is never read.\n\
+             [line 11] Error: Undefined variable 'fresh'.\n"
+                .into()
+        )
+    );
+}
+
+#[test]
+fn break_leaves_the_innermost_loop() {
+    let source = r#"
+        for (var i = 0; i < 3; i = i + 1) {
+            while (true) break;
+            if (i == 1) break;
+            print i;
+        }
+    "#;
+    assert_eq!(run(source), ("0\n".into(), "".into()));
+}
+
+#[test]
+fn plus_joins_strings_and_numbers_in_either_order() {
+    assert_eq!(evaluate(r#""n=" + 5"#).0, "n=5\n");
+    assert_eq!(evaluate(r#"5 + "!""#).0, "5!\n");
+    assert_eq!(evaluate(r#""pi " + 3.5"#).0, "pi 3.5\n");
+    assert_eq!(
+        evaluate(r#"true + "x""#).1,
+        "[line 1] Error: Operands must be two numbers or two strings, got true and \"x\".\n"
+    );
+}
+
+#[test]
+fn lambdas_can_be_called_as_soon_as_they_are_written() {
+    assert_eq!(
+        run("print (fun(x) { return x * 2; })(21);"),
+        ("42\n".into(), "".into())
+    );
+    let source = "fun twice(f, x) { return f(f(x)); }\nprint twice(fun(n) { return n + 1; }, 1);";
+    assert_eq!(run(source), ("3\n".into(), "".into()));
+}
+
+#[test]
+fn lists_are_built_read_written_and_bounds_checked() {
+    let list = "var list = [1, 2, 3];\n";
+    assert_eq!(
+        run(&format!("{list}print list[1];")),
+        ("2\n".into(), "".into())
+    );
+    assert_eq!(
+        run(&format!("{list}list[1] = 5;\nprint list[1];")),
+        ("5\n".into(), "".into())
+    );
+    assert_eq!(
+        run(&format!("{list}print list[3];")).1,
+        "[line 2] Error: List index 3 out of bounds for length 3.\n"
+    );
+    assert_eq!(
+        run(&format!("{list}list[-1] = 0;")).1,
+        "[line 2] Error: List index must be a non-negative integer, got -1.\n"
+    );
+}
+
+#[test]
+fn maps_insert_look_up_and_overwrite_entries() {
+    let source = r#"
+        var ages = {"ann": 31};
+        ages["bob"] = 42;
+        print ages["ann"];
+        print ages["bob"];
+        ages["ann"] = 32;
+        print ages["ann"];
+        print ages["cid"];
+    "#;
+    assert_eq!(run(source), ("31\n42\n32\nnil\n".into(), "".into()));
+    assert_eq!(
+        run("var ages = {\"ann\": 31};\nprint ages[1];").1,
+        "[line 2] Error: Map key must be a string, got 1.\n"
+    );
+}
+
+#[test]
+fn arity_errors_name_the_function_called() {
+    assert_eq!(
+        run("fun add(a, b) { return a + b; }\nadd(1);").1,
+        "[line 2] Error: Expected 2 arguments but got 1 when calling 'add'.\n"
+    );
+    assert_eq!(
+        run("var twice = fun(x) { return x * 2; };\ntwice();").1,
+        "[line 2] Error: Expected 1 arguments but got 0 when calling '<fn>'.\n"
+    );
+}
+
+#[test]
+fn anonymous_functions_are_named_fn_in_output_and_traces() {
+    assert_eq!(
+        run("var f = fun() { return 1 / nil; };
+print f;
+f();"),
+        (
+            "<fn>\n".into(),
+            "[line 1] Error: Operands must be numbers, got 1 and nil.\n    at <fn> (line 3)\n"
+                .into()
+        )
+    );
+}
+
+#[test]
+fn stray_closing_delimiters_are_reported_and_parsing_carries_on() {
+    assert_eq!(
+        run("}\nprint 1;\n)\nprint 2 +;\n"),
+        (
+            "".into(),
+            "[line 1] Error: Unexpected '}'.\n\
+             [line 3] Error: Unexpected ')'.\n\
+             [line 4] Error: at ';': Expect expression.\n"
+                .into()
+        )
+    );
+}
+
+#[test]
+fn check_lists_syntax_errors_without_running_anything() {
+    assert_eq!(
+        lox("check", "print 1 +;\nprint 2;\nprint 3 *;\n"),
+        (
+            "".into(),
+            "[line 1] Error: at ';': Expect expression.\n\
+             [line 3] Error: at ';': Expect expression.\n"
+                .into()
+        )
+    );
+    assert_eq!(lox("check", "print 1;\n"), ("".into(), "".into()));
+    assert_eq!(lox("check", ""), ("".into(), "".into()));
+    assert_eq!(
+        lox("check", "print 1; @\nprint 2;\n"),
+        (
+            "".into(),
+            "[line 1] Error: Unexpected character: @\n".into()
+        )
+    );
+}
+
+#[test]
+fn lists_maps_and_functions_are_equal_only_to_themselves() {
+    let source = r#"
+        var a = [1];
+        var b = a;
+        print a == b;
+        print a == [1];
+        var m = {"k": 1};
+        print m == m;
+        print m == {"k": 1};
+        fun f() {}
+        print f == f;
+        print "x" == "x" and 1 == 1.0 and nil == nil;
+    "#;
+    assert_eq!(
+        run(source),
+        ("true\nfalse\ntrue\nfalse\ntrue\ntrue\n".into(), "".into())
+    );
+}
+
+#[test]
+fn postfix_increment_gives_back_the_old_value() {
+    assert_eq!(
+        run("var i = 1;\nprint i++;\nprint i;\nprint i--;\nprint i;"),
+        ("1\n2\n2\n1\n".into(), "".into())
+    );
+    assert_eq!(
+        run("print 1++;").1,
+        "[line 1] Error: at '++': Invalid increment target.\n"
+    );
+    assert_eq!(
+        run("var s = \"a\";\ns++;").1,
+        "[line 2] Error: Operand must be a number, got \"a\".\n"
+    );
+}
+
+#[test]
+fn for_in_walks_list_elements_and_sorted_map_keys() {
+    let source = r#"var sum = 0;
+for (x in [1, 2, 3]) sum = sum + x;
+print sum;
+for (k in {"b": 2, "a": 1}) print k;
+for (n in 3) print n;"#;
+    assert_eq!(
+        run(source),
+        (
+            "6\na\nb\n".into(),
+            "[line 5] Error: Can only iterate over lists and maps, got 3.\n".into()
+        )
+    );
+}
+
+#[test]
+fn xor_runs_both_sides_and_gives_a_bool() {
+    let source = r#"print true xor true;
+print true xor nil;
+print nil xor 1;
+print false xor nil;
+fun side(v) { print "ran"; return v; }
+print side(true) xor side(true);"#;
+    assert_eq!(
+        run(source),
+        (
+            "false\ntrue\ntrue\nfalse\nran\nran\nfalse\n".into(),
+            "".into()
+        )
+    );
+}
+
+#[test]
+fn and_or_give_back_the_deciding_operand() {
+    let source = r#"print nil or "x";
+print nil and "x";
+print false or 2;
+print 1 and 2;
+fun f() { print "called"; return true; }
+print nil and f();
+print true or f();"#;
+    assert_eq!(run(source), ("x\nnil\n2\n2\nnil\ntrue\n".into(), "".into()));
+}
+
+#[test]
+fn numbers_print_without_a_trailing_fraction_or_exponent() {
+    let source = "print 3;\nprint 2.5;\nprint -0;\nprint 1000000000000000000000;\nprint 0.1 + 0.2;";
+    assert_eq!(
+        run(source),
+        (
+            "3\n2.5\n-0\n1000000000000000000000\n0.30000000000000004\n".into(),
+            "".into()
+        )
+    );
+}
+
+#[test]
+fn a_rest_parameter_collects_the_extra_arguments() {
+    let source = r#"fun f(a, ...rest) { print rest; }
+f(1);
+f(1, 2);
+f(1, 2, 3);
+var g = fun(...xs) { return len(xs); };
+print g();
+f();"#;
+    assert_eq!(
+        run(source),
+        (
+            "[]\n[2]\n[2, 3]\n0\n".into(),
+            "[line 7] Error: Expected at least 1 arguments but got 0 when calling 'f'.\n".into()
+        )
+    );
+}
+
+#[test]
+fn reading_a_variable_before_it_is_assigned_is_an_error() {
+    assert_eq!(
+        run("var a;\nprint a;").1,
+        "[line 2] Error: Uninitialized variable 'a'.\n"
+    );
+    assert_eq!(
+        run("var b;\nb = 1;\nprint b;\n{ var c; print c; }"),
+        (
+            "1\n".into(),
+            "[line 4] Error: Uninitialized variable 'c'.\n".into()
+        )
+    );
+}
+
+#[test]
+fn a_thousand_term_sum_parses_and_evaluates() {
+    let chain = format!("1{}", " + 1".repeat(999));
+    assert_eq!(evaluate(&chain), ("1000\n".into(), "".into()));
+}
+
+#[test]
+fn ten_thousand_nested_parentheses_fail_to_parse_instead_of_overflowing() {
+    let nested = format!("print {}1{};", "(".repeat(10_000), ")".repeat(10_000));
+    assert_eq!(
+        run(&nested),
+        (
+            "".into(),
+            "[line 1] Error: at '(': Too much nesting.\n".into()
+        )
+    );
+}
+
+#[test]
+fn a_hundred_thousand_statements_in_a_block_run_in_order() {
+    let source = format!(
+        "var n = 0;\n{{\n{}}}\nprint n;",
+        "n = n + 1;\n".repeat(100_000)
+    );
+    assert_eq!(run(&source), ("100000\n".into(), "".into()));
+}
+
+#[test]
+fn an_error_deep_in_an_expression_points_at_its_own_line() {
+    let source = "var a = 1;\nprint (a +\n  (2 * (\n    missing\n  )));";
+    assert_eq!(
+        run(source).1,
+        "[line 4] Error: Undefined variable 'missing'.\n"
+    );
+}
+
+#[test]
+fn print_joins_several_values_with_spaces() {
+    assert_eq!(
+        run("print 1;\nprint 1, \"two\";\nprint 1, \"two\", nil;"),
+        ("1\n1 two\n1 two nil\n".into(), "".into())
+    );
+}
+
+#[test]
+fn classes_build_instances_and_subclasses_call_super() {
+    let source = r#"class Empty {}
+class A {
+  init(x) { this.x = x; }
+  get() { return this.x; }
+}
+class B < A {
+  get() { return super.get() + 1; }
+}
+print Empty;
+print Empty();
+print A(1).get();
+print B(1).get();"#;
+    assert_eq!(
+        run(source),
+        ("<class Empty>\nEmpty instance\n1\n2\n".into(), "".into())
+    );
+}
+
+#[test]
+fn class_methods_are_called_on_the_class_and_cannot_see_this() {
+    assert_eq!(
+        run("class Math {\n  class square(n) { return n * n; }\n}\nprint Math.square(3);"),
+        ("9\n".into(), "".into())
+    );
+    assert_eq!(
+        run("class A {\n  class f() { return this; }\n}").1,
+        "[line 2] Error: at 'this': Can't use 'this' in a class method.\n"
+    );
+}
+
+#[test]
+fn getters_run_on_read_while_methods_still_need_a_call() {
+    let source = r#"class Rect {
+  init(w, h) { this.w = w; this.h = h; }
+  area { return this.w * this.h; }
+  name() { return "rect"; }
+}
+var r = Rect(2, 3);
+print r.area;
+print r.name;
+print r.name();"#;
+    assert_eq!(run(source), ("6\n<fn name>\nrect\n".into(), "".into()));
+}
+
+#[test]
+fn is_checks_the_class_and_its_superclasses() {
+    let source = r#"class A {}
+class B < A {}
+class C {}
+var b = B();
+print b is B;
+print b is A;
+print b is C;
+print A() is B;
+print 1 is A;
+print b is 1;"#;
+    assert_eq!(
+        run(source),
+        (
+            "true\ntrue\nfalse\nfalse\nfalse\n".into(),
+            "[line 10] Error: Right operand of 'is' must be a class, got 1.\n".into()
+        )
+    );
+}
+
+#[test]
+fn errors_inside_calls_print_the_call_stack() {
+    let source = "fun inner() {\n  return 1 / 0;\n}\nfun middle() {\n  return inner();\n}\nfun outer() {\n  return middle();\n}\nouter();";
+    assert_eq!(
+        run(source).1,
+        "[line 2] Error: Division by zero.\n    at inner (line 5)\n    at middle (line 8)\n    at outer (line 10)\n"
+    );
+}
+
+#[test]
+fn unbounded_recursion_is_a_stack_overflow_error() {
+    assert_eq!(
+        run("fun f() { return f(); }\nf();"),
+        (
+            "".into(),
+            "[line 1] Error: Stack overflow.\n    at f (line 1)\n    ... 510 more of the same\n    at f (line 2)\n"
+                .into()
+        )
+    );
+}
+
+#[test]
+fn closures_bind_the_variable_in_scope_where_they_are_written() {
+    let source = r#"fun makeCounter() {
+  var i = 0;
+  fun count() { i = i + 1; return i; }
+  return count;
+}
+var c = makeCounter();
+print c();
+print c();
+var a = "global";
+{
+  fun show() { print a; }
+  show();
+  var a = "block";
+  show();
+}"#;
+    assert_eq!(
+        run(source),
+        (
+            "1\n2\nglobal\nglobal\n".into(),
+            "[line 13] Warning: Unused variable 'a'.\n".into()
+        )
+    );
+}
+
+#[test]
+fn functions_classes_instances_and_natives_print_their_kind() {
+    assert_eq!(
+        run("fun f() {}\nclass C {}\nprint f;\nprint C;\nprint C();\nprint len;"),
+        (
+            "<fn f>\n<class C>\nC instance\n<native fn>\n".into(),
+            "".into()
+        )
+    );
+}
+
+#[test]
+fn raw_strings_keep_backslashes_and_r_stays_a_name() {
+    let source = r#"var r = 1;
+print r"C:\new\u{41}";
+print r + 1;"#;
+    assert_eq!(run(source), ("C:\\new\\u{41}\n2\n".into(), "".into()));
+}
+
+#[test]
+fn strings_interpolate_variables_and_expressions() {
+    let source = r#"var a = 2;
+var b = 3;
+print "a is ${a}";
+print "sum is ${a + b}, ${"quoted"}";"#;
+    assert_eq!(
+        run(source),
+        ("a is 2\nsum is 5, quoted\n".into(), "".into())
+    );
+    assert_eq!(
+        lox("tokenize", r#"print "a is ${a;"#).1,
+        "[line 1] Error: Unterminated interpolation.\n"
+    );
+}
+
+#[test]
+fn return_and_break_outside_their_construct_are_rejected() {
+    assert_eq!(
+        run("return 1;\nprint 2;"),
+        (
+            "".into(),
+            "[line 1] Error: at 'return': Can't return from top-level code.\n".into()
+        )
+    );
+    assert_eq!(
+        run("print 1;\nbreak;"),
+        (
+            "".into(),
+            "[line 2] Error: at 'break': Can't use 'break' outside of a loop.\n".into()
+        )
+    );
+}
+
+#[test]
+fn a_getter_that_reads_itself_overflows_cleanly() {
+    assert_eq!(
+        run("class A { g { return this.g; } }\nprint A().g;"),
+        (
+            "".into(),
+            "[line 1] Error: Stack overflow.\n    at g (line 1)\n    ... 510 more of the same\n    at g (line 2)\n"
+                .into()
+        )
+    );
+}
+
+#[test]
+fn a_list_that_contains_itself_prints_as_an_ellipsis() {
+    let source = r#"var a = [1];
+a[0] = a;
+print a;
+var b = [1, 2];
+var c = [b, b];
+print c;
+b[1] = c;
+print b;
+print str(a);"#;
+    assert_eq!(
+        run(source),
+        (
+            "[[...]]\n[[1, 2], [1, 2]]\n[1, [[...], [...]]]\n[[...]]\n".into(),
+            "".into()
+        )
+    );
+}
+
+#[test]
+fn a_map_that_contains_itself_prints_as_an_ellipsis() {
+    let source = r#"var m = {"a": 1};
+m["self"] = m;
+print m;
+var n = {};
+var l = [n];
+n["l"] = l;
+print n;
+print l;
+print str(m);"#;
+    assert_eq!(
+        run(source),
+        (
+            "{a: 1, self: {...}}\n{l: [{...}]}\n[{l: [...]}]\n{a: 1, self: {...}}\n".into(),
+            "".into()
+        )
+    );
+}
+
+#[test]
+fn long_sums_and_else_if_chains_are_not_too_deep() {
+    let sum = format!("1{}", " + 1".repeat(1_099));
+    assert_eq!(evaluate(&sum), ("1100\n".into(), "".into()));
+
+    let arms: String = (1..200)
+        .map(|i| format!(" else if (x == {i}) print {i};"))
+        .collect();
+    let chain = format!("var x = 199;\nif (x == 0) print 0;{arms}");
+    assert_eq!(run(&chain), ("199\n".into(), "".into()));
+}
+
+#[test]
+fn a_ten_thousand_arm_else_if_chain_runs_to_its_last_arms() {
+    let arms: String = (1..10_000)
+        .map(|i| format!(" else if (x == {i}) print {i};"))
+        .collect();
+    let chain = |x: i32| format!("var x = {x};\nif (x == 0) print 0;{arms} else print -1;");
+    assert_eq!(run(&chain(9_999)), ("9999\n".into(), "".into()));
+    assert_eq!(run(&chain(10_000)), ("-1\n".into(), "".into()));
+}
+
+#[test]
+fn to_string_methods_show_instances_and_must_return_strings() {
+    let source = r#"class Point {
+    init(x, y) { this.x = x; this.y = y; }
+    toString() { return "(${this.x}, ${this.y})"; }
+}
+class Broken { toString() { return 1; } }
+print Point(1, 2);
+print [Point(3, 4)];
+print Broken();"#;
+    assert_eq!(
+        run(source),
+        (
+            "(1, 2)\n[(3, 4)]\n".into(),
+            "[line 8] Error: toString must return a string, got 1.\n".into()
+        )
+    );
+}
+
+#[test]
+fn operators_call_the_left_operands_method_when_it_has_one() {
+    let source = "class Complex {
+    init(re, im) { this.re = re; this.im = im; }
+    add(other) { return Complex(this.re + other.re, this.im + other.im); }
+}
+var a = Complex(1, 2);
+var b = Complex(3, 4);
+print (a + b).im;
+print a - b;";
+    assert_eq!(
+        run(source),
+        (
+            "6\n".into(),
+            "[line 8] Error: Operands must be numbers, got Complex instance and Complex instance.\n"
+                .into()
+        )
+    );
+}
+
+#[test]
+fn binary_operands_are_evaluated_left_to_right() {
+    let source = r#"fun a() { print "a"; return 1; }
+fun b() { print "b"; return 2; }
+print a() - b();"#;
+    assert_eq!(run(source), ("a\nb\n-1\n".into(), "".into()));
+}
+
+#[test]
+fn loop_expressions_evaluate_to_what_break_gives_them() {
+    let source = "var x = loop { break 42; };
+var i = 0;
+var y = loop { i = i + 1; if (i == 3) break; };
+print x;
+print y;";
+    assert_eq!(run(source), ("42\nnil\n".into(), "".into()));
+    assert_eq!(
+        run("while (true) break 1;").1,
+        "[line 1] Error: at 'break': Can only break with a value out of a 'loop' expression.\n"
+    );
+}
+
+#[test]
+fn block_expressions_evaluate_to_their_trailing_expression() {
+    let source = r#"var x = { var a = 2; a * 3 };
+var y = { print x; };
+var m = {"a": 1};
+print y;
+print m;"#;
+    assert_eq!(run(source), ("6\nnil\n{a: 1}\n".into(), "".into()));
+}
+
+#[test]
+fn switch_runs_the_first_matching_arm_or_the_default() {
+    let source = r#"fun name(n) {
+    var seen = "";
+    switch (n) {
+        case 1: seen = seen + "one";
+        case 2, 3: seen = seen + "two or three";
+        default: seen = seen + "many";
+    }
+    return seen;
+}
+print name(1);
+print name(3);
+print name(9);"#;
+    assert_eq!(run(source), ("one\ntwo or three\nmany\n".into(), "".into()));
+}
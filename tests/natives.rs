@@ -0,0 +1,180 @@
+mod common;
+
+use common::{evaluate, run};
+
+#[test]
+fn assert_throws_passes_only_when_the_call_fails() {
+    let (stdout, _) =
+        run("fun fails() { return \"a\" - 1; }\nassert_throws(fails);\nprint \"ok\";");
+    assert_eq!(stdout, "ok\n");
+    assert_eq!(
+        run("fun fine() { return 1; }\nassert_throws(fine);\nprint \"ok\";"),
+        (
+            "".into(),
+            "[line 2] Error: Expected an error but none was raised.\n".into()
+        )
+    );
+}
+
+#[test]
+fn mod_takes_the_sign_of_the_divisor() {
+    assert_eq!(evaluate("mod(-1, 3)").0, "2\n");
+    assert_eq!(evaluate("mod(7, 3)").0, "1\n");
+    assert_eq!(evaluate("mod(7, -3)").0, "-2\n");
+    assert_eq!(evaluate("mod(1, 0)").1, "[line 1] Error: Modulo by zero.\n");
+}
+
+#[test]
+fn len_counts_characters_elements_and_entries() {
+    assert_eq!(evaluate(r#"len("héllo")"#).0, "5\n");
+    assert_eq!(evaluate("len([1, 2, 3])").0, "3\n");
+    assert_eq!(evaluate(r#"len({"a": 1})"#).0, "1\n");
+    assert_eq!(
+        evaluate("len(42)").1,
+        "[line 1] Error: Cannot take length of number.\n"
+    );
+}
+
+#[test]
+fn substr_and_char_at_count_characters() {
+    assert_eq!(evaluate(r#"substr("café", 2, 2)"#).0, "fé\n");
+    assert_eq!(evaluate(r#"charAt("café", 3)"#).0, "é\n");
+    assert_eq!(
+        evaluate(r#"substr("café", 3, 2)"#).1,
+        "[line 1] Error: Substring 3..5 out of bounds for length 4.\n"
+    );
+    assert_eq!(
+        evaluate(r#"substr("café", 1, pow(2, 64))"#).1,
+        "[line 1] Error: Substring 1..18446744073709551615 out of bounds for length 4.\n"
+    );
+    assert_eq!(
+        evaluate(r#"charAt("café", 4)"#).1,
+        "[line 1] Error: String index 4 out of bounds for length 4.\n"
+    );
+    assert_eq!(
+        evaluate(r#"charAt("café", -1)"#).1,
+        "[line 1] Error: Index must be a non-negative integer, got -1.\n"
+    );
+}
+
+#[test]
+fn type_names_the_kind_of_value() {
+    let source = r#"print type(1);
+print type("s");
+print type(true);
+print type(nil);
+print type([]);
+print type({});
+fun f() {}
+print type(f);
+print type(len);
+type();"#;
+    assert_eq!(
+        run(source),
+        (
+            "number\nstring\nboolean\nnil\nlist\nmap\nfunction\nfunction\n".into(),
+            "[line 10] Error: Expected 1 arguments but got 0 when calling 'type'.\n".into()
+        )
+    );
+}
+
+#[test]
+fn number_and_str_convert_between_strings_and_values() {
+    assert_eq!(evaluate(r#"number("42") + 1"#).0, "43\n");
+    assert_eq!(evaluate(r#"str(3) + "x""#).0, "3x\n");
+    assert_eq!(
+        run("print str(nil);\nprint str(true);\nprint str([1, 2]);").0,
+        "nil\ntrue\n[1, 2]\n"
+    );
+    assert_eq!(
+        evaluate(r#"number("abc")"#).1,
+        "[line 1] Error: Cannot convert \"abc\" to a number.\n"
+    );
+    assert_eq!(
+        evaluate("number(nil)").1,
+        "[line 1] Error: Cannot convert nil to a number.\n"
+    );
+}
+
+#[test]
+fn format_fills_each_placeholder_in_turn() {
+    assert_eq!(
+        evaluate(r#"format("{} + {} = {}", 1, 2, 3)"#).0,
+        "1 + 2 = 3\n"
+    );
+    assert_eq!(evaluate(r#"format("no holes")"#).0, "no holes\n");
+    assert_eq!(
+        evaluate(r#"format("{} and {}", "a")"#).1,
+        "[line 1] Error: Not enough arguments for format string.\n"
+    );
+}
+
+#[test]
+fn write_adds_no_separator_or_newline() {
+    assert_eq!(
+        run("write(\"a\");\nwrite(1);\nprint \"!\";"),
+        ("a1!\n".into(), "".into())
+    );
+}
+
+#[test]
+fn assert_fails_with_its_message_on_a_falsey_condition() {
+    assert_eq!(
+        run("assert(1 < 2);\nassert(true, \"fine\");\nprint \"ok\";\nassert(1 == 2, \"math is broken\");"),
+        (
+            "ok\n".into(),
+            "[line 4] Error: Assertion failed: math is broken\n".into()
+        )
+    );
+    assert_eq!(
+        run("var a = 1;\nassert(a > 2);").1,
+        "[line 2] Error: Assertion failed.\n"
+    );
+}
+
+#[test]
+fn math_natives_work_on_numbers_only() {
+    let source = r#"print sqrt(16);
+print floor(-1.5);
+print ceil(1.2);
+print round(2.5);
+print pow(2, 10);
+print min(3, 1);
+print max(1, 5, 3);
+print abs(-4);
+print abs("a");"#;
+    assert_eq!(
+        run(source),
+        (
+            "4\n-2\n2\n3\n1024\n1\n5\n4\n".into(),
+            "[line 9] Error: Argument must be a number, got \"a\".\n".into()
+        )
+    );
+}
+
+#[test]
+fn seeding_repeats_the_sequence_and_randint_stays_in_bounds() {
+    let source = r#"seed(42);
+var a = random();
+var b = randint(1, 6);
+seed(42);
+print a == random();
+print b == randint(1, 6);
+print a >= 0 and a < 1;
+var seen = [false, false, false];
+var ok = true;
+for (var i = 0; i < 1000; i++) {
+  var n = randint(1, 3);
+  if (n < 1 or n > 3 or n != floor(n)) ok = false;
+  else seen[n - 1] = true;
+}
+print ok and seen[0] and seen[1] and seen[2];
+randint(3, 1);"#;
+    assert_eq!(
+        run(source),
+        (
+            "true\ntrue\ntrue\ntrue\n".into(),
+            "[line 16] Error: Empty range for randint.\n".into()
+        )
+    );
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::{lox, run};
+
+fn check(source: &str) -> (String, String) {
+    lox("check", source)
+}
+
+#[test]
+fn else_if_chains_print_as_flat_elif_groups() {
+    assert_eq!(
+        lox("ast", "if (a) print 1; else if (b) print 2; else print 3;"),
+        (
+            "(if a (print 1.0) (elif b (print 2.0)) (print 3.0))\n".into(),
+            "".into()
+        )
+    );
+}
+
+#[test]
+fn statements_that_follow_one_another_do_not_nest() {
+    let source = format!("var n = 0;\n{}print n;", "n = n + 1;\n".repeat(100_000));
+    assert_eq!(check(&source), ("".into(), "".into()));
+    assert_eq!(run(&source), ("100000\n".into(), "".into()));
+    assert_eq!(lox("fmt", &source).0.lines().count(), 100_002);
+    assert_eq!(lox("ast", &source).0.lines().count(), 100_002);
+
+    let blocks = format!("{}{}", "{".repeat(10_000), "}".repeat(10_000));
+    assert_eq!(
+        check(&blocks),
+        (
+            "".into(),
+            "[line 1] Error: at '{': Too much nesting.\n".into()
+        )
+    );
+}
+
+#[test]
+fn return_is_only_allowed_inside_a_function() {
+    let top_level = "[line 1] Error: at 'return': Can't return from top-level code.\n";
+    assert_eq!(check("return 1; print \"after\";").1, top_level);
+    assert_eq!(check("print \"a\"; { return; } print \"b\";").1, top_level);
+    assert_eq!(check("fun f() { { return 1; } }"), ("".into(), "".into()));
+    assert_eq!(
+        check("var f = fun() { return 1; };"),
+        ("".into(), "".into())
+    );
+}
+
+#[test]
+fn calls_and_functions_take_at_most_255_arguments() {
+    let list = |count: usize| vec!["a"; count].join(", ");
+    assert_eq!(
+        check(&format!("fun f(...a) {{}}\nvar a;\nf({});", list(255))),
+        ("".into(), "".into())
+    );
+    assert_eq!(
+        check(&format!("f({});", list(256))).1,
+        "[line 1] Error: at 'a': Can't have more than 255 arguments.\n"
+    );
+    assert_eq!(
+        check(&format!("fun f({}) {{}}", list(256))).1,
+        "[line 1] Error: at 'a': Can't have more than 255 parameters.\n"
+    );
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use common::repl;
+
+#[test]
+fn expression_statements_echo_their_value() {
+    assert_eq!(
+        repl("1+1;\nvar a = 3;\na = 4;\na;\nprint a;\n"),
+        ("> 2\n> > > 4\n> 4\n> ".into(), "".into())
+    );
+    assert_eq!(repl("1 + 2\n"), ("> 3\n> ".into(), "".into()));
+}
+
+#[test]
+fn open_brackets_strings_and_comments_carry_on_to_the_next_line() {
+    assert_eq!(
+        repl("{ var x = 1;\nprint x; }\nprint \"a\nb\";\n"),
+        ("> ... 1\n> ... a\nb\n> ".into(), "".into())
+    );
+    assert_eq!(
+        repl("/* a note\nstill */ print 1;\n"),
+        ("> ... 1\n> ".into(), "".into())
+    );
+}
+
+#[test]
+fn a_failed_assignment_leaves_a_constant_as_it_was() {
+    assert_eq!(
+        repl("const PI = 3.14;\nPI = 3;\nprint PI;\n"),
+        (
+            "> > > 3.14\n> ".into(),
+            "[line 1] Error: Cannot assign to constant 'PI'.\n".into()
+        )
+    );
+}
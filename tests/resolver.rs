@@ -0,0 +1,138 @@
+mod common;
+
+use common::{lox, run};
+
+fn check(source: &str) -> (String, String) {
+    lox("check", source)
+}
+
+#[test]
+fn a_store_overwritten_before_any_read_is_dead() {
+    assert_eq!(
+        run("{\n  var x = 1;\n  x = 2;\n  print x;\n}"),
+        (
+            "2\n".into(),
+            "[line 2] Warning: Value assigned to 'x' is never read.\n".into()
+        )
+    );
+    assert_eq!(
+        run("{\n  var x = 1;\n  print x;\n  x = 2;\n  print x;\n}"),
+        ("1\n2\n".into(), "".into())
+    );
+}
+
+#[test]
+fn only_locals_may_not_be_declared_twice_in_one_scope() {
+    assert_eq!(
+        run("{\n  var a = 1;\n  var a = 2;\n  print a;\n}").1,
+        "[line 3] Error: at 'a': Already a variable with this name in this scope.\n\
+         [line 2] Warning: Value assigned to 'a' is never read.\n"
+    );
+    assert_eq!(
+        run("var a = 1;\nvar a = 2;\nprint a;"),
+        (
+            "2\n".into(),
+            "[line 1] Warning: Value assigned to 'a' is never read.\n".into()
+        )
+    );
+}
+
+#[test]
+fn reading_a_getter_counts_as_reading_the_globals_it_uses() {
+    let source = "var g = 1;\nclass R { area { return g; } }\nvar r = R();\n\
+                  g = 2;\nvar a = r.area;\ng = 3;\nprint a + g;";
+    assert_eq!(run(source), ("5\n".into(), "".into()));
+}
+
+#[test]
+fn a_call_may_read_the_locals_its_closures_captured() {
+    assert_eq!(
+        run("fun f() { var x = 1; fun g() { print x; } x = 2; g(); x = 3; g(); }\nf();"),
+        ("2\n3\n".into(), "".into())
+    );
+    assert_eq!(
+        run("fun f() { var x = 1; fun g() { print x; } x = 2; x = 3; g(); }\nf();"),
+        (
+            "3\n".into(),
+            "[line 1] Warning: Value assigned to 'x' is never read.\n".into()
+        )
+    );
+}
+
+#[test]
+fn assigning_to_a_constant_is_an_error_not_a_dead_store() {
+    assert_eq!(
+        run("const PI = 3;\nPI = 4;"),
+        (
+            "".into(),
+            "[line 2] Error: Cannot assign to constant 'PI'.\n".into()
+        )
+    );
+    assert_eq!(
+        run("{\n  const PI = 3;\n  PI = 4;\n  print PI;\n}").1,
+        "[line 3] Error: Cannot assign to constant 'PI'.\n"
+    );
+}
+
+#[test]
+fn the_statement_after_one_that_always_leaves_is_unreachable() {
+    assert_eq!(
+        check("fun f() {\n  return 1;\n  print 2;\n}"),
+        ("".into(), "[line 3] Warning: Unreachable code.\n".into())
+    );
+    assert_eq!(
+        check("while (true) {\n  if (true) break; else continue;\n  print 1;\n}").1,
+        "[line 3] Warning: Unreachable code.\n"
+    );
+    assert_eq!(
+        check("fun f(a) {\n  if (a) return 1;\n  print 2;\n}"),
+        ("".into(), "".into())
+    );
+}
+
+#[test]
+fn locals_nothing_reads_are_unused_but_globals_are_not() {
+    assert_eq!(
+        check("var g = 0;\n{\n  var x = 1;\n}").1,
+        "[line 3] Warning: Unused variable 'x'.\n"
+    );
+    assert_eq!(
+        check("{\n  var x = 1;\n  print x;\n}"),
+        ("".into(), "".into())
+    );
+    assert_eq!(
+        check("fun f() {\n  var x = 1;\n  return fun() { return x; };\n}\nf();"),
+        ("".into(), "".into())
+    );
+}
+
+#[test]
+fn a_global_store_read_by_to_string_or_an_operator_method_is_live() {
+    assert_eq!(
+        check("var x = 1;\nx = 2;\nprint x;").1,
+        "[line 1] Warning: Value assigned to 'x' is never read.\n"
+    );
+    assert_eq!(
+        check("var x = 1;\nprint x;\nx = 2;\nprint x;"),
+        ("".into(), "".into())
+    );
+
+    let shown = "var g = 1;\nclass Show {\n  toString() { return str(g); }\n}\nvar s = Show();";
+    assert_eq!(
+        check(&format!("{shown}\nprint s;\ng = 2;\nprint g;")),
+        ("".into(), "".into())
+    );
+    assert_eq!(
+        check(&format!(
+            "{shown}\nvar t = \"${{s}}\";\ng = 2;\nprint t + g;"
+        )),
+        ("".into(), "".into())
+    );
+    assert_eq!(
+        check(
+            "var g = 1;\nclass V { add(other) { return g; } }\nvar v = V();\n\
+             g = 2;\nvar sum = v + v;\ng = 3;\nprint sum + g;"
+        ),
+        ("".into(), "".into())
+    );
+}
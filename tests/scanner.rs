@@ -0,0 +1,162 @@
+mod common;
+
+use common::{lox, run};
+
+fn tokenize(source: &str) -> (String, String) {
+    lox("tokenize", source)
+}
+
+#[test]
+fn question_mark_and_colon_are_tokens() {
+    assert_eq!(
+        tokenize("? :"),
+        (
+            "QUESTION ? null\nCOLON : null\nEOF  null\n".into(),
+            "".into()
+        )
+    );
+}
+
+#[test]
+fn an_unterminated_string_is_reported_at_its_opening_line() {
+    assert_eq!(
+        tokenize("print 1;\nprint \"one\ntwo\nthree").1,
+        "[line 2] Error: Unterminated string.\n"
+    );
+}
+
+#[test]
+fn square_brackets_are_tokens() {
+    assert_eq!(
+        tokenize("[]").0,
+        "LEFT_BRACKET [ null\nRIGHT_BRACKET ] null\nEOF  null\n"
+    );
+}
+
+#[test]
+fn underscores_separate_digits() {
+    assert_eq!(
+        tokenize("1_000_000 3.141_592"),
+        (
+            "NUMBER 1_000_000 1000000.0\nNUMBER 3.141_592 3.141592\nEOF  null\n".into(),
+            "".into()
+        )
+    );
+    for misplaced in ["1__0", "1_", "1_.5", "1._5"] {
+        assert_eq!(
+            tokenize(misplaced),
+            (
+                "EOF  null\n".into(),
+                "[line 1] Error: Invalid numeric separator.\n".into()
+            ),
+            "{misplaced}"
+        );
+    }
+}
+
+#[test]
+fn hexadecimal_and_binary_literals_keep_their_lexeme() {
+    assert_eq!(
+        tokenize("0xFF 0b101"),
+        (
+            "NUMBER 0xFF 255.0\nNUMBER 0b101 5.0\nEOF  null\n".into(),
+            "".into()
+        )
+    );
+    assert_eq!(
+        tokenize("0xG").1,
+        "[line 1] Error: Invalid hexadecimal literal.\n"
+    );
+    assert_eq!(
+        tokenize("0b102").1,
+        "[line 1] Error: Invalid binary literal.\n"
+    );
+}
+
+#[test]
+fn hexadecimal_and_binary_digits_take_separators_and_grow_past_f64s_integers() {
+    assert_eq!(
+        tokenize("0b1111_0000 0xFFFFFFFFFFFFFFFFFF"),
+        (
+            "NUMBER 0b1111_0000 240.0\n\
+             NUMBER 0xFFFFFFFFFFFFFFFFFF 4722366482869645000000.0\n\
+             EOF  null\n"
+                .into(),
+            "".into()
+        )
+    );
+    assert_eq!(
+        tokenize("0x").1,
+        "[line 1] Error: Invalid hexadecimal literal.\n"
+    );
+    assert_eq!(
+        tokenize("0b1__0").1,
+        "[line 1] Error: Invalid numeric separator.\n"
+    );
+    // Only ASCII digits belong to the number, so `é` starts a name.
+    assert_eq!(
+        tokenize("0xFé"),
+        (
+            "NUMBER 0xF 15.0\nIDENTIFIER é null\nEOF  null\n".into(),
+            "".into()
+        )
+    );
+}
+
+#[test]
+fn identifiers_may_use_unicode_letters_but_not_emoji() {
+    assert_eq!(
+        tokenize("var π = 3;"),
+        (
+            "VAR var null\nIDENTIFIER π null\nEQUAL = null\nNUMBER 3 3.0\nSEMICOLON ; null\nEOF  null\n"
+                .into(),
+            "".into()
+        )
+    );
+    assert_eq!(
+        tokenize("😀").1,
+        "[line 1] Error: Unexpected character: 😀\n"
+    );
+}
+
+#[test]
+fn unicode_escapes_decode_to_their_code_point() {
+    assert_eq!(
+        tokenize(r#""\u{48}\u{e9}\u{1F600}""#),
+        (
+            "STRING \"\\u{48}\\u{e9}\\u{1F600}\" Hé😀\nEOF  null\n".into(),
+            "".into()
+        )
+    );
+    assert_eq!(
+        tokenize(r#""\u{110000}""#).1,
+        "[line 1] Error: Invalid unicode escape.\n"
+    );
+}
+
+#[test]
+fn triple_quoted_strings_span_lines_and_report_where_they_open() {
+    let source = "var poem = \"\"\"\nroses \"red\"\n  violets\"\"\";\nprint poem;";
+    assert_eq!(
+        run(source),
+        ("roses \"red\"\n  violets\n".into(), "".into())
+    );
+    assert_eq!(
+        tokenize("var a;\n\"\"\"open\n\n").1,
+        "[line 2] Error: Unterminated string.\n"
+    );
+}
+
+#[test]
+fn an_empty_file_still_scans_to_an_eof_token() {
+    assert_eq!(tokenize(""), ("EOF  null\n".into(), "".into()));
+    assert_eq!(
+        lox("tokens-json", ""),
+        (
+            "[\n  {\"kind\": \"EOF\", \"lexeme\": \"\", \"literal\": null, \"line\": 1}\n]\n"
+                .into(),
+            "".into()
+        )
+    );
+    assert_eq!(run(""), ("".into(), "".into()));
+}